@@ -0,0 +1,85 @@
+// Download bandwidth cap shared by every download
+
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+/// Token bucket limiting how fast downloads may read from the network
+///
+/// One limiter is shared by the whole queue, so parallel downloads split the
+/// cap between them instead of each getting the full rate.
+pub struct BandwidthLimiter {
+    bytes_per_sec: u64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// Bytes that may be read right now; negative while paying off a large chunk
+    available: f64,
+    updated: Instant,
+}
+
+impl BandwidthLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        Self {
+            bytes_per_sec,
+            bucket: Mutex::new(Bucket {
+                available: bytes_per_sec as f64,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Account for `bytes` just read, waiting until the cap allows reading more
+    ///
+    /// The bucket holds at most one second's worth of bytes, so an idle period
+    /// doesn't turn into a burst above the cap. Waiters are served in order.
+    pub async fn consume(&self, bytes: u64) {
+        let rate = self.bytes_per_sec as f64;
+        let mut bucket = self.bucket.lock().await;
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * rate;
+        bucket.available = (bucket.available + refill).min(rate) - bytes as f64;
+        bucket.updated = now;
+        if bucket.available < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-bucket.available / rate)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Consume `total` bytes in 50 KB reads
+    async fn read(limiter: &BandwidthLimiter, total: u64) {
+        for _ in 0..total / 50_000 {
+            limiter.consume(50_000).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn reading_past_the_first_second_waits_for_the_cap() {
+        let limiter = BandwidthLimiter::new(1_000_000);
+        let started = Instant::now();
+
+        // The first second's worth is free, the rest takes half a second at 1 MB/s
+        read(&limiter, 1_500_000).await;
+
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(490), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+    }
+
+    #[tokio::test]
+    async fn parallel_readers_share_the_cap() {
+        let limiter = BandwidthLimiter::new(1_000_000);
+        let started = Instant::now();
+
+        tokio::join!(read(&limiter, 750_000), read(&limiter, 750_000));
+
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(490), "{elapsed:?}");
+    }
+}
@@ -0,0 +1,216 @@
+// Games list with an on-disk cache for offline browsing
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::api::{ApiClient, GamesFetch};
+use crate::error::SdrError;
+use crate::models::Game;
+use crate::paths;
+use crate::persist;
+
+/// File name of the cached games list inside the app config dir
+pub const CACHE_FILE: &str = "games_cache.json";
+
+/// Last successfully fetched games list, as stored in `games_cache.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedGames {
+    pub games: Vec<Game>,
+    pub last_fetched: DateTime<Utc>,
+    /// `ETag` the server sent with this list, sent back as `If-None-Match`
+    #[serde(default)]
+    pub etag: Option<String>,
+}
+
+/// Games list returned to the frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GamesList {
+    pub games: Vec<Game>,
+    /// When the list was last fetched from the server
+    pub last_fetched: DateTime<Utc>,
+    /// `true` if the server couldn't be reached and this is the cached list
+    pub from_cache: bool,
+}
+
+/// Default location of the games cache
+pub fn cache_path() -> Result<PathBuf, SdrError> {
+    Ok(paths::config_dir()?.join(CACHE_FILE))
+}
+
+/// Read the cached list, if there is a readable one
+pub fn load_cache(path: &Path) -> Option<CachedGames> {
+    persist::load_json(path)
+}
+
+pub fn save_cache(path: &Path, cache: &CachedGames) -> Result<(), SdrError> {
+    persist::save_json(path, cache)
+}
+
+/// Fetch the live list and cache it, or fall back to the cache if the fetch fails
+///
+/// The cached list's `ETag` is sent along, so an unchanged list isn't downloaded again.
+pub async fn refresh(api: &ApiClient, cache_path: &Path) -> Result<GamesList, SdrError> {
+    let cached = load_cache(cache_path);
+    let etag = cached.as_ref().and_then(|cache| cache.etag.as_deref());
+    let fetched = match api.fetch_games_since(etag).await {
+        Ok(GamesFetch::Modified { games, etag }) => Ok(CachedGames {
+            games,
+            last_fetched: Utc::now(),
+            etag,
+        }),
+        Ok(GamesFetch::NotModified) => match cached.clone() {
+            Some(cache) => Ok(CachedGames {
+                last_fetched: Utc::now(),
+                ..cache
+            }),
+            None => Err(SdrError::Internal(String::from(
+                "Game server reported the list unchanged, but there is no cached list",
+            ))),
+        },
+        Err(e) => Err(e),
+    };
+
+    match fetched {
+        Ok(cache) => {
+            if let Err(e) = save_cache(cache_path, &cache) {
+                tracing::warn!("{e}");
+            }
+            Ok(GamesList {
+                games: cache.games,
+                last_fetched: cache.last_fetched,
+                from_cache: false,
+            })
+        }
+        Err(e) => match cached {
+            Some(cache) => {
+                tracing::warn!("{e}, using the cached games list");
+                Ok(GamesList {
+                    games: cache.games,
+                    last_fetched: cache.last_fetched,
+                    from_cache: true,
+                })
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Look up a single game in the list `refresh` returns
+///
+/// Goes through the cache, so an unchanged list costs a `304` rather than a
+/// full download.
+pub async fn find_game(
+    api: &ApiClient,
+    cache_path: &Path,
+    game_date: &str,
+) -> Result<Game, SdrError> {
+    refresh(api, cache_path)
+        .await?
+        .games
+        .into_iter()
+        .find(|game| game.date == game_date)
+        .ok_or_else(|| {
+            SdrError::NotFound(format!("Game {game_date} is not available on the server"))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::api::RetryPolicy;
+    use crate::config::ServerConfig;
+    use crate::test_support::{game, games_body, MockServer, Response};
+
+    #[tokio::test]
+    async fn refresh_falls_back_to_the_cache_once_the_server_fails() {
+        let games = vec![game("a", "2024-01-01"), game("b", "2024-01-02")];
+        let body = games_body(&games);
+        let failing = Arc::new(AtomicBool::new(false));
+        let server = MockServer::start({
+            let failing = failing.clone();
+            move |_| match failing.load(Ordering::SeqCst) {
+                true => Response::new(500),
+                false => Response::json(body.clone()),
+            }
+        })
+        .await;
+        let api = ApiClient::new(ServerConfig::new([&server.url])).with_retry(RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join(CACHE_FILE);
+
+        let fresh = refresh(&api, &cache_path).await.unwrap();
+        assert_eq!(fresh.games, games);
+        assert!(!fresh.from_cache);
+
+        failing.store(true, Ordering::SeqCst);
+        let cached = refresh(&api, &cache_path).await.unwrap();
+        assert_eq!(cached.games, games);
+        assert!(cached.from_cache);
+        assert_eq!(cached.last_fetched, fresh.last_fetched);
+        assert_eq!(server.hits(), 2);
+    }
+
+    #[tokio::test]
+    async fn refresh_without_a_cache_reports_the_failure() {
+        let server = MockServer::start(|_| Response::new(500)).await;
+        let api = ApiClient::new(ServerConfig::new([&server.url])).with_retry(RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        });
+        let dir = tempfile::tempdir().unwrap();
+
+        let error = refresh(&api, &dir.path().join(CACHE_FILE))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), "Http", "{error}");
+    }
+
+    #[test]
+    fn games_list_is_sent_as_camel_case() {
+        let list = GamesList {
+            games: Vec::new(),
+            last_fetched: Utc::now(),
+            from_cache: true,
+        };
+        let json = serde_json::to_value(&list).unwrap();
+        assert_eq!(json["fromCache"], true);
+        assert!(json.get("lastFetched").is_some());
+    }
+
+    #[tokio::test]
+    async fn find_game_revalidates_the_cached_list() {
+        let games = vec![game("a", "2024-01-01"), game("b", "2024-01-02")];
+        let body = games_body(&games);
+        let server = MockServer::start(move |request| match request.header("If-None-Match") {
+            Some("\"v1\"") => Response::new(304),
+            _ => Response::json(body.clone()).header("ETag", "\"v1\""),
+        })
+        .await;
+        let api = ApiClient::new(ServerConfig::new([&server.url]));
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join(CACHE_FILE);
+
+        let first = find_game(&api, &cache_path, "2024-01-02").await.unwrap();
+        let second = find_game(&api, &cache_path, "2024-01-02").await.unwrap();
+
+        assert_eq!(first, games[1]);
+        assert_eq!(second, games[1]);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("If-None-Match"), None);
+        assert_eq!(requests[1].header("If-None-Match"), Some("\"v1\""));
+        let error = find_game(&api, &cache_path, "2030-01-01")
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), "NotFound");
+    }
+}
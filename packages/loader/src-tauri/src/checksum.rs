@@ -0,0 +1,101 @@
+// SHA-256 verification of downloaded games
+
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+use crate::error::SdrError;
+
+/// Lowercase hex encoding of a finished hash
+pub fn to_hex(hasher: Sha256) -> String {
+    format!("{:x}", hasher.finalize())
+}
+
+/// Feed the contents of `path` into `hasher`
+pub async fn update_from_file(hasher: &mut Sha256, path: &Path) -> Result<(), SdrError> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| SdrError::Io(format!("Failed to open {}: {e}", path.display())))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buf)
+            .await
+            .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", path.display())))?;
+        if read == 0 {
+            return Ok(());
+        }
+        hasher.update(&buf[..read]);
+    }
+}
+
+/// SHA-256 of a file on disk
+pub async fn hash_file(path: &Path) -> Result<String, SdrError> {
+    let mut hasher = Sha256::new();
+    update_from_file(&mut hasher, path).await?;
+    Ok(to_hex(hasher))
+}
+
+/// SHA-256 of a file on disk, for use off the async runtime
+pub fn hash_file_blocking(path: &Path) -> Result<String, SdrError> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| SdrError::Io(format!("Failed to open {}: {e}", path.display())))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", path.display())))?;
+    Ok(to_hex(hasher))
+}
+
+/// Compare a computed hash against the one the server advertised
+pub fn verify(game_date: &str, expected: &str, actual: &str) -> Result<(), SdrError> {
+    if expected.trim().eq_ignore_ascii_case(actual) {
+        Ok(())
+    } else {
+        Err(SdrError::ChecksumMismatch {
+            game_date: game_date.to_string(),
+            expected: expected.trim().to_string(),
+            actual: actual.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SHA-256 of `b"hello"`
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    #[tokio::test]
+    async fn matching_hash_verifies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.zip");
+        std::fs::write(&path, b"hello").unwrap();
+
+        let actual = hash_file(&path).await.unwrap();
+        assert_eq!(actual, HELLO_SHA256);
+        assert_eq!(hash_file_blocking(&path).unwrap(), HELLO_SHA256);
+        // Servers may send uppercase hex or trailing whitespace
+        verify(
+            "2024-01-01",
+            &format!("{}\n", HELLO_SHA256.to_uppercase()),
+            &actual,
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn mismatching_hash_reports_both_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.zip");
+        std::fs::write(&path, b"hellO").unwrap();
+
+        let actual = hash_file(&path).await.unwrap();
+        let error = verify("2024-01-01", HELLO_SHA256, &actual).unwrap_err();
+        assert_eq!(error.kind(), "ChecksumMismatch");
+        let message = error.to_string();
+        assert!(message.contains(HELLO_SHA256), "{message}");
+        assert!(message.contains(&actual), "{message}");
+    }
+}
@@ -0,0 +1,152 @@
+// Removing leftovers of interrupted downloads
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::config::ConfigStore;
+use crate::download::ActiveDownloads;
+use crate::error::SdrError;
+use crate::paths;
+
+/// Partial downloads untouched for this long are considered abandoned
+pub const STALE_PART_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Suffix of an archive that is still being downloaded, see `paths::part_path`
+const PART_SUFFIX: &str = ".zip.part";
+
+/// What `cleanup_partial_downloads` removed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupReport {
+    pub removed_files: usize,
+    pub reclaimed_bytes: u64,
+}
+
+/// Delete `.part` files in `games_dir` last written more than `max_age` ago
+///
+/// Partials of queued or running downloads are kept whatever their age, and so
+/// are recent ones, which a retried download can still resume.
+pub fn cleanup_partial_downloads(
+    games_dir: &Path,
+    active: &ActiveDownloads,
+    max_age: Duration,
+) -> Result<CleanupReport, SdrError> {
+    let entries = match std::fs::read_dir(games_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(CleanupReport::default()),
+        Err(e) => {
+            return Err(SdrError::Io(format!(
+                "Failed to read {}: {e}",
+                games_dir.display()
+            )))
+        }
+    };
+
+    let now = SystemTime::now();
+    let mut report = CleanupReport::default();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        let Some(game_date) = name.strip_suffix(PART_SUFFIX) else {
+            continue;
+        };
+        if active.is_active(game_date) {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+        if !metadata.is_file() || age < max_age {
+            continue;
+        }
+
+        let path = entry.path();
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                report.removed_files += 1;
+                report.reclaimed_bytes += metadata.len();
+            }
+            Err(e) => tracing::warn!("failed to remove {}: {e}", path.display()),
+        }
+    }
+    Ok(report)
+}
+
+/// Run `cleanup_partial_downloads` in the background when the app starts
+pub fn spawn_startup_cleanup<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = paths::games_dir(&app.state::<ConfigStore>().get()).and_then(|games_dir| {
+            cleanup_partial_downloads(&games_dir, &app.state::<ActiveDownloads>(), STALE_PART_AGE)
+        });
+        match result {
+            Ok(report) if report.removed_files > 0 => tracing::info!(
+                "removed {} stale partial downloads, reclaiming {} bytes",
+                report.removed_files,
+                report.reclaimed_bytes
+            ),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("cleaning up partial downloads failed: {e}"),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a partial download of `game_date` last modified `age` ago
+    fn write_part(games_dir: &Path, game_date: &str, size: usize, age: Duration) {
+        let path = paths::part_path(games_dir, game_date);
+        std::fs::write(&path, vec![0; size]).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[test]
+    fn removes_only_old_partial_downloads() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
+        write_part(games_dir.path(), "2024-01-01", 100, 2 * day);
+        write_part(games_dir.path(), "2024-01-02", 200, 3 * day);
+        write_part(games_dir.path(), "2024-01-03", 400, Duration::from_secs(60));
+        write_part(games_dir.path(), "2024-01-04", 800, 2 * day);
+        let active = ActiveDownloads::default();
+        let _token = active.start("2024-01-04").unwrap();
+
+        let report = cleanup_partial_downloads(games_dir.path(), &active, STALE_PART_AGE).unwrap();
+
+        assert_eq!(
+            report,
+            CleanupReport {
+                removed_files: 2,
+                reclaimed_bytes: 300,
+            }
+        );
+        let part_exists = |game_date| paths::part_path(games_dir.path(), game_date).exists();
+        assert!(!part_exists("2024-01-01"));
+        assert!(!part_exists("2024-01-02"));
+        assert!(part_exists("2024-01-03"));
+        assert!(part_exists("2024-01-04"));
+    }
+
+    #[test]
+    fn a_missing_games_dir_has_nothing_to_clean() {
+        let parent = tempfile::tempdir().unwrap();
+        let report = cleanup_partial_downloads(
+            &parent.path().join("games"),
+            &ActiveDownloads::default(),
+            STALE_PART_AGE,
+        )
+        .unwrap();
+        assert_eq!(report, CleanupReport::default());
+    }
+}
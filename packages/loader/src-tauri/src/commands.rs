@@ -1,19 +1,748 @@
 // Tauri commands for the Steam Deck Randomizer loader
 
-/// Fetch the list of available games from the server
-pub fn fetch_games() -> String {
-    // TODO: HTTP request to game server
-    String::from("[]")
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tokio_util::sync::CancellationToken;
+
+use crate::api::ApiClient;
+use crate::catalog::{self, GamesList};
+use crate::cleanup::{self, CleanupReport};
+use crate::config::{Config, ConfigStore};
+use crate::disk;
+use crate::download::{self, ActiveDownloads, DownloadPlan};
+use crate::error::SdrError;
+use crate::favorites::Favorites;
+use crate::health::{self, HealthReport};
+use crate::import;
+use crate::install;
+use crate::launcher::{self, RunningGames};
+use crate::models::{Game, InstalledGame, UpdateInfo};
+use crate::paths;
+use crate::persist;
+use crate::profile::{self, Profile};
+use crate::prune::{self, PruneReport};
+use crate::queue::{Batch, DownloadQueue, QueueItem};
+use crate::randomizer::{self, History, WeightMode};
+use crate::search;
+use crate::stats::{GameStats, StatsStore};
+use crate::sync::AutoSync;
+use crate::thumbnails;
+use crate::updates;
+use crate::verify::{self, GameVerification};
+
+/// Fetch the list of available games from the server, or the cached list when offline
+#[tauri::command]
+pub async fn fetch_games(api: State<'_, ApiClient>) -> Result<GamesList, SdrError> {
+    refresh_games(&api, &catalog::cache_path()?).await
+}
+
+/// `catalog::refresh`, logging how it went
+#[tracing::instrument(skip_all)]
+async fn refresh_games(api: &ApiClient, cache_path: &Path) -> Result<GamesList, SdrError> {
+    let started = Instant::now();
+    let result = catalog::refresh(api, cache_path).await;
+    match &result {
+        Ok(list) => tracing::info!(
+            games = list.games.len(),
+            from_cache = list.from_cache,
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "games list fetched"
+        ),
+        Err(e) => tracing::error!(kind = e.kind(), "fetching the games list failed: {e}"),
+    }
+    result
+}
+
+/// Games whose title fuzzily matches `query`, ranked best first
+///
+/// Searches the cached list, only fetching when nothing has been cached yet.
+#[tauri::command]
+pub async fn search_games(api: State<'_, ApiClient>, query: String) -> Result<Vec<Game>, SdrError> {
+    let games = cached_games(&api).await?;
+    Ok(search::search_games(&games, &query))
+}
+
+/// Games dated within `from`..=`to` (`YYYY-MM-DD`), either bound optional
+#[tauri::command]
+pub async fn filter_games(
+    api: State<'_, ApiClient>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<Game>, SdrError> {
+    let games = cached_games(&api).await?;
+    search::filter_games(&games, from.as_deref(), to.as_deref())
+}
+
+/// Local path of a game's thumbnail, downloaded on first use
+///
+/// `None` means the game has no thumbnail or it couldn't be fetched.
+#[tauri::command]
+pub async fn get_thumbnail(
+    api: State<'_, ApiClient>,
+    game_date: String,
+) -> Result<Option<String>, SdrError> {
+    let game = cached_games(&api)
+        .await?
+        .into_iter()
+        .find(|game| game.date == game_date)
+        .ok_or_else(|| SdrError::NotFound(format!("Game {game_date} is not in the games list")))?;
+    let path = thumbnails::get_thumbnail(&api, &paths::thumbnails_dir()?, &game).await?;
+    Ok(path.map(|path| path.display().to_string()))
+}
+
+/// The cached games list, or a fresh one if there is no cache yet
+async fn cached_games(api: &ApiClient) -> Result<Vec<Game>, SdrError> {
+    let cache_path = catalog::cache_path()?;
+    match catalog::load_cache(&cache_path) {
+        Some(cache) => Ok(cache.games),
+        None => Ok(catalog::refresh(api, &cache_path).await?.games),
+    }
+}
+
+/// Result of `download_game`: where the game went, or what a dry run found
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum DownloadOutcome {
+    Installed(String),
+    DryRun(DownloadPlan),
+}
+
+/// Download and extract a specific game, emitting `download-progress` events along the way
+///
+/// With `dry_run` set, only checks size, free space and any existing install,
+/// and returns the plan without writing anything. Fails if the game is already
+/// downloading or queued.
+#[tauri::command]
+pub async fn download_game(
+    app: AppHandle,
+    api: State<'_, ApiClient>,
+    config: State<'_, ConfigStore>,
+    game_date: String,
+    dry_run: Option<bool>,
+) -> Result<DownloadOutcome, SdrError> {
+    paths::check_game_date(&game_date)?;
+    if dry_run.unwrap_or(false) {
+        let game = catalog::find_game(&api, &catalog::cache_path()?, &game_date).await?;
+        let games_dir = paths::planned_games_dir(&config.get())?;
+        let plan = download::plan_download(&api, &games_dir, &game).await?;
+        return Ok(DownloadOutcome::DryRun(plan));
+    }
+    app.state::<ActiveDownloads>().start(&game_date)?;
+    let path = run_download(&app, &api, &game_date, &CancellationToken::new()).await?;
+    Ok(DownloadOutcome::Installed(path.display().to_string()))
+}
+
+/// Add a game to the download queue; it starts once a download slot is free
+///
+/// Fails if the game is already downloading or queued.
+#[tauri::command]
+pub async fn enqueue_download(
+    app: AppHandle,
+    queue: State<'_, DownloadQueue>,
+    game_date: String,
+) -> Result<(), SdrError> {
+    paths::check_game_date(&game_date)?;
+    // Register up front so a queued download can be cancelled before it starts
+    let cancel = app.state::<ActiveDownloads>().start(&game_date)?;
+    let (app_for_job, date) = (app.clone(), game_date.clone());
+    let enqueued = queue.enqueue(&game_date, cancel, move |pause| {
+        let (app, date) = (app_for_job.clone(), date.clone());
+        async move {
+            let api = app.state::<ApiClient>();
+            run_download(&app, &api, &date, &pause).await.map(|_| ())
+        }
+    });
+    if !enqueued {
+        // The job never runs, so nothing else would release the slot
+        app.state::<ActiveDownloads>().finish(&game_date);
+        return Err(SdrError::Invalid(format!(
+            "{game_date} is already downloading or queued"
+        )));
+    }
+    Ok(())
+}
+
+/// Queue several games at once, returning the ones actually queued
+///
+/// Duplicates and games already downloading are dropped, and games already
+/// installed are skipped unless `force` is set. Once every queued download has
+/// ended, a `batch-complete` event summarizes which succeeded, failed or were
+/// skipped.
+#[tauri::command]
+pub async fn download_games(
+    app: AppHandle,
+    queue: State<'_, DownloadQueue>,
+    config: State<'_, ConfigStore>,
+    dates: Vec<String>,
+    force: Option<bool>,
+) -> Result<Vec<String>, SdrError> {
+    for date in &dates {
+        paths::check_game_date(date)?;
+    }
+    let games_dir = paths::games_dir(&config.get())?;
+    let force = force.unwrap_or(false);
+
+    let batch = Arc::new(Batch::default());
+    let selected = batch.select(dates, |game_date| {
+        !force && paths::game_dir(&games_dir, game_date).is_dir()
+    });
+    let mut queued = Vec::new();
+    for game_date in selected {
+        let Ok(cancel) = app.state::<ActiveDownloads>().start(&game_date) else {
+            batch.skip(&game_date);
+            continue;
+        };
+        batch.add();
+        let (app_for_job, batch_for_job, date) = (app.clone(), batch.clone(), game_date.clone());
+        let enqueued = queue.enqueue(&game_date, cancel, move |pause| {
+            let (app, batch, date) = (app_for_job.clone(), batch_for_job.clone(), date.clone());
+            async move {
+                let api = app.state::<ApiClient>();
+                let result = run_download(&app, &api, &date, &pause).await.map(|_| ());
+                if matches!(result, Err(SdrError::Paused(_))) {
+                    // Runs again on resume, the batch only counts the final outcome
+                    return result;
+                }
+                if let Some(summary) = batch.finish(&date, &result) {
+                    let _ = app.emit("batch-complete", summary);
+                }
+                result
+            }
+        });
+        if enqueued {
+            queued.push(game_date);
+        } else {
+            // Already queued on its own, so this batch won't hear back about it
+            app.state::<ActiveDownloads>().finish(&game_date);
+            batch.skip_added(&game_date);
+        }
+    }
+    if let Some(summary) = batch.seal() {
+        let _ = app.emit("batch-complete", summary);
+    }
+    Ok(queued)
+}
+
+/// Stop a queued or running download and remove its partial file
+///
+/// Cancelling a game that isn't downloading is a no-op.
+#[tauri::command]
+pub async fn cancel_download(
+    active: State<'_, ActiveDownloads>,
+    game_date: String,
+) -> Result<(), SdrError> {
+    active.cancel(&game_date);
+    Ok(())
+}
+
+/// Halt the download queue, keeping partial downloads so they can resume
+#[tauri::command]
+pub async fn pause_queue(queue: State<'_, DownloadQueue>) -> Result<(), SdrError> {
+    queue.pause();
+    Ok(())
+}
+
+/// Continue the download queue, resuming paused downloads from their partial files
+#[tauri::command]
+pub async fn resume_queue(queue: State<'_, DownloadQueue>) -> Result<(), SdrError> {
+    queue.resume();
+    Ok(())
+}
+
+/// State of every download the queue has seen
+#[tauri::command]
+pub async fn queue_status(queue: State<'_, DownloadQueue>) -> Result<Vec<QueueItem>, SdrError> {
+    Ok(queue.status())
+}
+
+/// Resolve a game on the server and download it into the games directory
+///
+/// Cancelling `pause` halts the download with `SdrError::Paused`, and it stays
+/// registered as active so its partial file is kept.
+#[tracing::instrument(skip(app, api, pause))]
+async fn run_download<R: Runtime>(
+    app: &AppHandle<R>,
+    api: &ApiClient,
+    game_date: &str,
+    pause: &CancellationToken,
+) -> Result<PathBuf, SdrError> {
+    let started = Instant::now();
+    let active = app.state::<ActiveDownloads>();
+    let cancel = active.token(game_date);
+    let result = async {
+        let config = app.state::<ConfigStore>().get();
+        let games_dir = paths::games_dir(&config)?;
+        let speed_window = Duration::from_secs(
+            config
+                .speed_window_secs
+                .unwrap_or(download::DEFAULT_SPEED_WINDOW_SECS)
+                .max(1),
+        );
+        tokio::select! {
+            biased;
+            // Already cancelled when the queue hands over a download it was holding
+            () = cancel.cancelled() => Err(download::discard_cancelled(&games_dir, game_date, app).await),
+            result = async {
+                let game = catalog::find_game(api, &catalog::cache_path()?, game_date).await?;
+                download::download_game(api, &games_dir, &game, app, &cancel, pause, speed_window)
+                    .await
+            } => result,
+        }
+    }
+    .await;
+    if !matches!(result, Err(SdrError::Paused(_))) {
+        active.finish(game_date);
+    }
+    match &result {
+        Ok(path) => tracing::info!(
+            path = %path.display(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "game installed"
+        ),
+        Err(SdrError::Cancelled(_)) => tracing::info!("download cancelled"),
+        Err(SdrError::Paused(_)) => tracing::info!("download paused"),
+        Err(e) => tracing::error!(kind = e.kind(), "download failed: {e}"),
+    }
+    result
+}
+
+/// Re-hash an installed game's files against the hashes recorded at install
+///
+/// The archive itself is deleted once unpacked, so this checks what is on disk.
+#[tauri::command]
+pub async fn verify_game(
+    config: State<'_, ConfigStore>,
+    game_date: String,
+) -> Result<GameVerification, SdrError> {
+    paths::check_game_date(&game_date)?;
+    let dir = paths::game_dir(&paths::games_dir(&config.get())?, &game_date);
+    let game = install::read_installed_game(&dir, game_date.clone())
+        .ok_or_else(|| SdrError::NotFound(format!("{game_date} is not installed")))?;
+    verify::verify_installed(vec![game])
+        .await?
+        .pop()
+        .ok_or_else(|| SdrError::Internal(format!("Verification of {game_date} was skipped")))
+}
+
+/// Re-hash every installed game and report which ones are intact
+#[tauri::command]
+pub async fn verify_installed(
+    config: State<'_, ConfigStore>,
+) -> Result<Vec<GameVerification>, SdrError> {
+    let games_dir = paths::games_dir(&config.get())?;
+    let installed =
+        tauri::async_runtime::spawn_blocking(move || install::list_installed_games(&games_dir))
+            .await
+            .map_err(|e| SdrError::Internal(format!("Scanning installed games failed: {e}")))??;
+    verify::verify_installed(installed).await
+}
+
+/// Installed games with a newer build on the server, checked against the cached list when offline
+#[tauri::command]
+pub async fn check_updates(
+    api: State<'_, ApiClient>,
+    config: State<'_, ConfigStore>,
+) -> Result<Vec<UpdateInfo>, SdrError> {
+    let games_dir = paths::games_dir(&config.get())?;
+    let installed = install::list_installed_games(&games_dir)?;
+    if installed.is_empty() {
+        return Ok(Vec::new());
+    }
+    let catalog = catalog::refresh(&api, &catalog::cache_path()?).await?;
+    Ok(updates::check_updates(&installed, &catalog.games))
+}
+
+/// Install a game from a folder already on disk, copying it unless `move_files` is set
+#[tauri::command]
+pub async fn import_game(
+    config: State<'_, ConfigStore>,
+    source_path: String,
+    game_date: String,
+    move_files: Option<bool>,
+    overwrite: Option<bool>,
+) -> Result<InstalledGame, SdrError> {
+    let games_dir = paths::games_dir(&config.get())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        import::import_game(
+            &games_dir,
+            &PathBuf::from(source_path),
+            &game_date,
+            move_files.unwrap_or(false),
+            overwrite.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| SdrError::Internal(format!("Importing the game failed: {e}")))?
+}
+
+/// Total bytes used by the games directory
+#[tauri::command]
+pub async fn installed_size(config: State<'_, ConfigStore>) -> Result<u64, SdrError> {
+    let games_dir = paths::games_dir(&config.get())?;
+    tauri::async_runtime::spawn_blocking(move || install::installed_size(&games_dir))
+        .await
+        .map_err(|e| SdrError::Internal(format!("Measuring the games directory failed: {e}")))?
+}
+
+/// Bytes it would take to download every game in the catalog
+#[tauri::command]
+pub async fn catalog_size(api: State<'_, ApiClient>) -> Result<u64, SdrError> {
+    let games = cached_games(&api).await?;
+    Ok(games.iter().map(|game| game.size_bytes).sum())
+}
+
+/// Delete partial downloads abandoned more than a day ago
+#[tauri::command]
+pub async fn cleanup_partial_downloads(
+    app: AppHandle,
+    config: State<'_, ConfigStore>,
+) -> Result<CleanupReport, SdrError> {
+    let games_dir = paths::games_dir(&config.get())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        cleanup::cleanup_partial_downloads(
+            &games_dir,
+            &app.state::<ActiveDownloads>(),
+            cleanup::STALE_PART_AGE,
+        )
+    })
+    .await
+    .map_err(|e| SdrError::Internal(format!("Cleaning up partial downloads failed: {e}")))?
+}
+
+/// Games currently installed in the games directory
+#[tauri::command]
+pub async fn list_installed_games(
+    config: State<'_, ConfigStore>,
+) -> Result<Vec<InstalledGame>, SdrError> {
+    let games_dir = paths::games_dir(&config.get())?;
+    tauri::async_runtime::spawn_blocking(move || install::list_installed_games(&games_dir))
+        .await
+        .map_err(|e| SdrError::Internal(format!("Scanning installed games failed: {e}")))?
+}
+
+/// Pick a random game, from the installed ones or the server's list if none are
+///
+/// Passing the same `seed` reproduces the same pick. Unless `avoid_recent` is
+/// `false`, games picked recently are skipped while others remain.
+/// `weight_mode` can favor the games played the fewest times, and
+/// `favorites_only` limits the pick to starred games.
+#[tauri::command]
+pub async fn pick_random_game(
+    api: State<'_, ApiClient>,
+    config: State<'_, ConfigStore>,
+    stats: State<'_, StatsStore>,
+    seed: Option<u64>,
+    avoid_recent: Option<bool>,
+    weight_mode: Option<WeightMode>,
+    favorites_only: Option<bool>,
+) -> Result<Game, SdrError> {
+    let cache_path = catalog::cache_path()?;
+    let games_dir = paths::games_dir(&config.get())?;
+    let installed = install::list_installed_games(&games_dir)?;
+    let mut pool = if installed.is_empty() {
+        catalog::refresh(&api, &cache_path).await?.games
+    } else {
+        let catalog = catalog::load_cache(&cache_path)
+            .map(|cache| cache.games)
+            .unwrap_or_default();
+        randomizer::installed_pool(&installed, &catalog)
+    };
+    if favorites_only.unwrap_or(false) {
+        pool = Favorites::load(&Favorites::path()?).filter(pool);
+        if pool.is_empty() {
+            return Err(SdrError::NotFound(String::from(
+                "None of your favorites are available",
+            )));
+        }
+    }
+
+    let stats = stats.get();
+    History::update(&History::path()?, |history| {
+        if avoid_recent.unwrap_or(true) {
+            pool = randomizer::exclude_recent(pool, history);
+        }
+        let game =
+            randomizer::pick_random_game(&pool, seed, weight_mode.unwrap_or_default(), &stats)?;
+        history.record(&game.date);
+        Ok(game)
+    })?
+}
+
+/// Star a game; it doesn't have to be in the catalog yet
+#[tauri::command]
+pub async fn add_favorite(game_date: String) -> Result<Vec<String>, SdrError> {
+    paths::check_game_date(&game_date)?;
+    Favorites::update(&Favorites::path()?, |favorites| {
+        favorites.add(&game_date);
+        favorites.game_dates.iter().cloned().collect()
+    })
+}
+
+/// Unstar a game; unstarring one that isn't starred is a no-op
+#[tauri::command]
+pub async fn remove_favorite(game_date: String) -> Result<Vec<String>, SdrError> {
+    Favorites::update(&Favorites::path()?, |favorites| {
+        favorites.remove(&game_date);
+        favorites.game_dates.iter().cloned().collect()
+    })
+}
+
+/// Dates of every starred game, sorted
+#[tauri::command]
+pub async fn list_favorites() -> Result<Vec<String>, SdrError> {
+    Ok(Favorites::load(&Favorites::path()?)
+        .game_dates
+        .into_iter()
+        .collect())
+}
+
+/// Write favorites, history and the shareable settings to `path`
+#[tauri::command]
+pub async fn export_profile(config: State<'_, ConfigStore>, path: String) -> Result<(), SdrError> {
+    let profile = Profile::new(
+        Favorites::load(&Favorites::path()?),
+        History::load(&History::path()?),
+        config.get(),
+    );
+    profile::export_profile(&PathBuf::from(path), &profile)
+}
+
+/// Load a profile written by `export_profile`, merging it in unless `replace` is set
+///
+/// Imported settings take effect the next time the loader starts.
+#[tauri::command]
+pub async fn import_profile(
+    config: State<'_, ConfigStore>,
+    path: String,
+    replace: Option<bool>,
+) -> Result<Profile, SdrError> {
+    let imported = profile::read_profile(&PathBuf::from(path))?;
+    let (favorites_path, history_path) = (Favorites::path()?, History::path()?);
+    let _updating = persist::lock_updates();
+    let mut favorites = Favorites::load(&favorites_path);
+    let mut history = History::load(&history_path);
+    let replace = replace.unwrap_or(false);
+    let updated = config.update(|config| {
+        profile::merge_profile(&mut favorites, &mut history, config, imported, replace)
+    })?;
+    favorites.save(&favorites_path)?;
+    history.save(&history_path)?;
+    Ok(Profile::new(favorites, history, updated))
+}
+
+/// Game of the day for today (UTC), the same for everyone with the same catalog
+#[tauri::command]
+pub async fn daily_game(api: State<'_, ApiClient>) -> Result<Game, SdrError> {
+    randomizer::daily_game(&cached_games(&api).await?)
+}
+
+/// Game of the day for any `YYYY-MM-DD` date
+#[tauri::command]
+pub async fn daily_game_for(api: State<'_, ApiClient>, date: String) -> Result<Game, SdrError> {
+    let date = search::parse_date(&date)?;
+    randomizer::daily_game_for(&cached_games(&api).await?, date)
+}
+
+/// Forget recent picks so every game is eligible again
+#[tauri::command]
+pub async fn clear_history() -> Result<(), SdrError> {
+    History::default().save(&History::path()?)
+}
+
+/// Start an installed game, returning the PID of its process
+///
+/// Counts as a play in the stats, with the play time added when the game exits.
+#[tauri::command]
+#[tracing::instrument(skip(app, config, stats))]
+pub async fn launch_game(
+    app: AppHandle,
+    config: State<'_, ConfigStore>,
+    stats: State<'_, StatsStore>,
+    game_date: String,
+) -> Result<u32, SdrError> {
+    let date = game_date.clone();
+    let running = app.state::<RunningGames>();
+    // Marked before the launch, so a game that exits right away is never left marked
+    running.start(&game_date);
+    let launched = paths::games_dir(&config.get()).and_then(|games_dir| {
+        let app = app.clone();
+        launcher::launch_game(&games_dir, &game_date, move |duration| {
+            tracing::info!(game_date = %date, duration_secs = duration.as_secs(), "game exited");
+            app.state::<RunningGames>().finish(&date);
+            let stats = app.state::<StatsStore>();
+            if let Err(e) = stats.update(|stats| stats.record_time(&date, duration.as_secs())) {
+                tracing::warn!("couldn't record play time of {date}: {e}");
+            }
+        })
+    });
+    let pid = launched.inspect_err(|e| {
+        running.finish(&game_date);
+        tracing::error!(kind = e.kind(), "launch failed: {e}");
+    })?;
+    tracing::info!(pid, "game launched");
+    if let Err(e) = stats.update(|stats| stats.record_start(&game_date)) {
+        tracing::warn!("couldn't record play of {game_date}: {e}");
+    }
+    Ok(pid)
+}
+
+/// Record a play of `game_date` that lasted `duration_secs`
+#[tauri::command]
+pub async fn record_play(
+    stats: State<'_, StatsStore>,
+    game_date: String,
+    duration_secs: u64,
+) -> Result<(), SdrError> {
+    paths::check_game_date(&game_date)?;
+    stats.update(|stats| {
+        stats.record_start(&game_date);
+        stats.record_time(&game_date, duration_secs);
+    })?;
+    Ok(())
 }
 
-/// Download a specific game's assets
-pub fn download_game(_game_date: &str) -> Result<String, String> {
-    // TODO: Download game files from server
-    Ok(String::from("downloaded"))
+/// Play statistics of every game, most played first
+#[tauri::command]
+pub async fn get_stats(stats: State<'_, StatsStore>) -> Result<Vec<GameStats>, SdrError> {
+    Ok(stats.get().sorted())
 }
 
-/// Get the local games directory path
-pub fn get_games_dir() -> String {
-    // TODO: Return platform-specific games directory
-    String::from("./games")
+/// Delete an installed game, returning the number of bytes freed
+#[tauri::command]
+pub async fn uninstall_game(
+    config: State<'_, ConfigStore>,
+    game_date: String,
+) -> Result<u64, SdrError> {
+    let games_dir = paths::games_dir(&config.get())?;
+    tauri::async_runtime::spawn_blocking(move || install::uninstall_game(&games_dir, &game_date))
+        .await
+        .map_err(|e| SdrError::Internal(format!("Uninstall failed: {e}")))?
+}
+
+/// Uninstall the least recently played games until `target_free_bytes` are free
+///
+/// With `dry_run`, only reports what would be removed.
+#[tauri::command]
+pub async fn prune_to_free(
+    app: AppHandle,
+    config: State<'_, ConfigStore>,
+    stats: State<'_, StatsStore>,
+    target_free_bytes: u64,
+    dry_run: Option<bool>,
+) -> Result<PruneReport, SdrError> {
+    let games_dir = paths::games_dir(&config.get())?;
+    let stats = stats.get();
+    let favorites = Favorites::load(&Favorites::path()?);
+    tauri::async_runtime::spawn_blocking(move || {
+        let active = app.state::<ActiveDownloads>();
+        let running = app.state::<RunningGames>();
+        prune::prune_to_free(
+            &games_dir,
+            target_free_bytes,
+            &stats,
+            &favorites,
+            |game_date| active.is_active(game_date) || running.is_running(game_date),
+            dry_run.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| SdrError::Internal(format!("Pruning games failed: {e}")))?
+}
+
+/// Bytes free on the filesystem holding `path`
+#[tauri::command]
+pub async fn free_space(path: String) -> Result<u64, SdrError> {
+    disk::free_space(&PathBuf::from(path))
+}
+
+/// Get the local games directory path, creating it if needed
+#[tauri::command]
+pub async fn get_games_dir(config: State<'_, ConfigStore>) -> Result<String, SdrError> {
+    Ok(paths::games_dir(&config.get())?.display().to_string())
+}
+
+/// Move future installs to `path`, or back to the platform default when `None`
+#[tauri::command]
+pub async fn set_games_dir(
+    config: State<'_, ConfigStore>,
+    path: Option<String>,
+) -> Result<Config, SdrError> {
+    let games_dir = path.filter(|p| !p.trim().is_empty()).map(PathBuf::from);
+    if let Some(dir) = &games_dir {
+        paths::ensure_writable(dir)?;
+    }
+    config.update(|config| config.games_dir = games_dir)
+}
+
+/// Pause or resume the background games list sync, remembered across restarts
+#[tauri::command]
+pub async fn set_auto_sync(
+    config: State<'_, ConfigStore>,
+    auto_sync: State<'_, AutoSync>,
+    enabled: bool,
+) -> Result<Config, SdrError> {
+    let updated = config.update(|config| config.auto_sync = Some(enabled))?;
+    auto_sync.set_enabled(enabled);
+    Ok(updated)
+}
+
+/// Check the server connection and local setup, reporting every problem found
+#[tauri::command]
+pub async fn health_check(
+    api: State<'_, ApiClient>,
+    config: State<'_, ConfigStore>,
+) -> Result<HealthReport, SdrError> {
+    Ok(health::health_check(&api, &config.get()).await)
+}
+
+/// Current loader config
+#[tauri::command]
+pub async fn get_config(config: State<'_, ConfigStore>) -> Result<Config, SdrError> {
+    Ok(config.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::Level;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    use super::*;
+    use crate::api::RetryPolicy;
+    use crate::config::ServerConfig;
+    use crate::test_support::{MockServer, Response};
+
+    /// Layer remembering the level of every event
+    #[derive(Clone, Default)]
+    struct Levels(Arc<Mutex<Vec<Level>>>);
+
+    impl<S: tracing::Subscriber> Layer<S> for Levels {
+        fn on_event(&self, event: &tracing::Event<'_>, _: Context<'_, S>) {
+            self.0.lock().unwrap().push(*event.metadata().level());
+        }
+    }
+
+    #[tokio::test]
+    async fn a_failing_fetch_is_logged_as_an_error() {
+        let levels = Levels::default();
+        let _subscriber =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(levels.clone()));
+        let server = MockServer::start(|_| Response::new(500)).await;
+        let api = ApiClient::new(ServerConfig::new([&server.url])).with_retry(RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        });
+        let dir = tempfile::tempdir().unwrap();
+
+        let result = refresh_games(&api, &dir.path().join(catalog::CACHE_FILE)).await;
+
+        assert!(result.is_err());
+        assert!(levels.0.lock().unwrap().contains(&Level::ERROR));
+    }
 }
@@ -0,0 +1,212 @@
+// Loader configuration
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SdrError;
+use crate::paths;
+use crate::persist;
+
+/// File name of the user config inside the app config dir
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Game server used when no override is configured
+pub const DEFAULT_SERVER_URL: &str = "http://localhost:2567";
+
+/// Environment variable that points the loader at a different server (e.g. staging)
+pub const SERVER_URL_ENV: &str = "SDR_SERVER_URL";
+
+/// Where the game server lives
+///
+/// Holds the base URL of every mirror, in the order they should be tried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub mirrors: Vec<String>,
+}
+
+impl ServerConfig {
+    pub fn new<S: AsRef<str>>(mirrors: impl IntoIterator<Item = S>) -> Self {
+        let mirrors: Vec<String> = mirrors
+            .into_iter()
+            .map(|url| url.as_ref().trim().trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty())
+            .collect();
+        if mirrors.is_empty() {
+            Self::default()
+        } else {
+            Self { mirrors }
+        }
+    }
+
+    /// `SDR_SERVER_URL` if set, then the configured mirrors, else the default server
+    pub fn resolve(config: &Config) -> Self {
+        let env_url = std::env::var(SERVER_URL_ENV).ok();
+        Self::new(env_url.iter().chain(&config.mirrors))
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            mirrors: vec![DEFAULT_SERVER_URL.to_string()],
+        }
+    }
+}
+
+/// Endpoint listing all available games
+pub fn games_url(base_url: &str) -> String {
+    format!("{base_url}/api/games")
+}
+
+/// Endpoint answering `200` while the server is up
+pub fn health_url(base_url: &str) -> String {
+    format!("{base_url}/api/health")
+}
+
+/// Archive containing a single game's files
+pub fn game_archive_url(base_url: &str, game_date: &str) -> String {
+    format!("{base_url}/games/{game_date}.zip")
+}
+
+/// User settings persisted to `config.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Overrides the platform games directory, e.g. to keep games on the SD card
+    pub games_dir: Option<PathBuf>,
+    /// Attempts made for each games list fetch before giving up
+    pub fetch_attempts: Option<u32>,
+    /// Base URLs of the game server mirrors, tried in order
+    pub mirrors: Vec<String>,
+    /// Sent to the game server as `Authorization: Bearer <key>`
+    pub api_key: Option<String>,
+    /// Refresh the games list in the background; on unless set to `false`
+    pub auto_sync: Option<bool>,
+    /// Minutes between two background refreshes
+    pub sync_interval_mins: Option<u64>,
+    /// Cap on the combined speed of all downloads; unlimited if unset
+    pub max_download_bytes_per_sec: Option<u64>,
+    /// Seconds a request may take, or a download may wait for data
+    pub request_timeout_secs: Option<u64>,
+    /// Seconds to wait for a connection to the game server
+    pub connect_timeout_secs: Option<u64>,
+    /// SHA-256 fingerprint the game server's TLS certificate must match
+    pub cert_pin: Option<String>,
+    /// Downloads the queue runs at the same time
+    pub max_parallel_downloads: Option<usize>,
+    /// Seconds of download the speed in progress events is averaged over
+    pub speed_window_secs: Option<u64>,
+    /// Most requests a minute sent to the game server, `0` for no cap
+    pub max_requests_per_minute: Option<u32>,
+    /// Proxy for all requests (`http://`, `https://` or `socks5://`), which may
+    /// embed credentials; the environment's proxy is used when unset
+    pub proxy_url: Option<String>,
+    /// WebSocket endpoint pushing games list changes; polling only when unset.
+    /// Ignored while `proxy_url` is set, and must be `wss://` if `api_key` is
+    pub ws_url: Option<String>,
+}
+
+impl Config {
+    /// This config with only the preferences that are safe to share
+    ///
+    /// Drops the API key and everything deciding which servers are trusted and
+    /// where files go: mirrors, WebSocket URL, certificate pin, proxy and games
+    /// directory. A shared profile can't point another loader at a server.
+    pub fn shareable(self) -> Self {
+        let mut shared = Config::default();
+        shared.replace_preferences(self);
+        shared
+    }
+
+    /// Fill in every preference left unset here from `other`
+    ///
+    /// The settings `shareable` drops are never taken from `other`.
+    pub fn merge_missing(&mut self, other: Config) {
+        let Config {
+            games_dir: _,
+            fetch_attempts,
+            mirrors: _,
+            api_key: _,
+            auto_sync,
+            sync_interval_mins,
+            max_download_bytes_per_sec,
+            request_timeout_secs,
+            connect_timeout_secs,
+            cert_pin: _,
+            max_parallel_downloads,
+            speed_window_secs,
+            max_requests_per_minute,
+            proxy_url: _,
+            ws_url: _,
+        } = other;
+        self.fetch_attempts = self.fetch_attempts.or(fetch_attempts);
+        self.auto_sync = self.auto_sync.or(auto_sync);
+        self.sync_interval_mins = self.sync_interval_mins.or(sync_interval_mins);
+        self.max_download_bytes_per_sec = self
+            .max_download_bytes_per_sec
+            .or(max_download_bytes_per_sec);
+        self.request_timeout_secs = self.request_timeout_secs.or(request_timeout_secs);
+        self.connect_timeout_secs = self.connect_timeout_secs.or(connect_timeout_secs);
+        self.max_parallel_downloads = self.max_parallel_downloads.or(max_parallel_downloads);
+        self.speed_window_secs = self.speed_window_secs.or(speed_window_secs);
+        self.max_requests_per_minute = self.max_requests_per_minute.or(max_requests_per_minute);
+    }
+
+    /// Take every preference from `other`, set or not
+    ///
+    /// The settings `shareable` drops are kept as they are here.
+    pub fn replace_preferences(&mut self, other: Config) {
+        let local = std::mem::take(self);
+        *self = Config {
+            games_dir: local.games_dir,
+            mirrors: local.mirrors,
+            api_key: local.api_key,
+            cert_pin: local.cert_pin,
+            proxy_url: local.proxy_url,
+            ws_url: local.ws_url,
+            ..other
+        };
+    }
+}
+
+/// The loaded config and where it is saved, held in Tauri managed state
+pub struct ConfigStore {
+    path: PathBuf,
+    config: RwLock<Config>,
+}
+
+impl ConfigStore {
+    /// Load `config.toml` from the app config dir
+    pub fn open() -> Result<Self, SdrError> {
+        Ok(Self::load(paths::config_dir()?.join(CONFIG_FILE)))
+    }
+
+    /// Load the config at `path`, falling back to defaults if it's missing or invalid
+    pub fn load(path: PathBuf) -> Self {
+        Self {
+            config: RwLock::new(persist::load_toml(&path)),
+            path,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Snapshot of the current config
+    pub fn get(&self) -> Config {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Apply `change` and write the result back to disk
+    pub fn update(&self, change: impl FnOnce(&mut Config)) -> Result<Config, SdrError> {
+        let mut config = self.config.write().unwrap();
+        let mut updated = config.clone();
+        change(&mut updated);
+        persist::save_toml(&self.path, &updated)?;
+        *config = updated.clone();
+        Ok(updated)
+    }
+}
@@ -0,0 +1,76 @@
+// Free space checks for the games directory
+
+use std::path::Path;
+
+use crate::error::SdrError;
+
+/// Extra headroom kept free on top of what a download needs
+pub const SAFETY_MARGIN_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Bytes available to the current user on the filesystem holding `path`
+pub fn free_space(path: &Path) -> Result<u64, SdrError> {
+    fs2::available_space(path).map_err(|e| {
+        SdrError::Io(format!(
+            "Failed to query free space for {}: {e}",
+            path.display()
+        ))
+    })
+}
+
+/// Space needed to install a game whose archive is `size_bytes` long
+///
+/// Counts the archive and its extracted contents, which briefly coexist.
+pub fn required_space(size_bytes: u64) -> u64 {
+    size_bytes
+        .saturating_mul(2)
+        .saturating_add(SAFETY_MARGIN_BYTES)
+}
+
+/// Fail if `available` bytes can't hold `required`
+pub fn check_space(path: &Path, available: u64, required: u64) -> Result<(), SdrError> {
+    if available >= required {
+        Ok(())
+    } else {
+        Err(SdrError::InsufficientSpace {
+            path: path.to_path_buf(),
+            required,
+            available,
+        })
+    }
+}
+
+/// Fail before downloading if `dir` can't hold a game of `size_bytes`
+pub fn ensure_space(dir: &Path, size_bytes: u64) -> Result<(), SdrError> {
+    check_space(dir, free_space(dir)?, required_space(size_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_space_rejects_too_little_room() {
+        let path = Path::new("/games");
+        let required = required_space(500 * 1024 * 1024);
+
+        let error = check_space(path, required - 1, required).unwrap_err();
+
+        assert!(
+            matches!(
+                &error,
+                SdrError::InsufficientSpace { available, .. } if *available == required - 1
+            ),
+            "{error:?}"
+        );
+        assert_eq!(
+            error.to_string(),
+            "Insufficient space in /games: 1100 MiB needed, 1099 MiB free"
+        );
+    }
+
+    #[test]
+    fn check_space_accepts_exactly_enough_room() {
+        let required = required_space(1024);
+        assert!(check_space(Path::new("/games"), required, required).is_ok());
+    }
+}
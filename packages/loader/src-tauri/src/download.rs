@@ -0,0 +1,885 @@
+// Streaming game downloads with progress reporting
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Runtime};
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::api::{self, ApiClient};
+use crate::checksum;
+use crate::config;
+use crate::disk;
+use crate::error::SdrError;
+use crate::extract::{self, ExtractProgress};
+use crate::install;
+use crate::models::Game;
+use crate::paths;
+
+/// Minimum time between two progress events
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Minimum bytes between two progress events
+const PROGRESS_BYTES: u64 = 256 * 1024;
+
+/// Seconds of the download the reported speed is averaged over, unless configured
+pub const DEFAULT_SPEED_WINDOW_SECS: u64 = 5;
+
+/// Payload of the `download-progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub game_date: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    /// Average speed over the configured window, `0` until it can be measured
+    pub bytes_per_sec: u64,
+    /// Left out while the total size or the speed is unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta_secs: Option<u64>,
+}
+
+impl DownloadProgress {
+    fn new(game_date: &str, downloaded_bytes: u64, total_bytes: Option<u64>) -> Self {
+        Self {
+            game_date: game_date.to_string(),
+            downloaded_bytes,
+            total_bytes,
+            bytes_per_sec: 0,
+            eta_secs: None,
+        }
+    }
+
+    /// Set the speed and the time left at that speed
+    fn set_speed(&mut self, bytes_per_sec: u64) {
+        self.bytes_per_sec = bytes_per_sec;
+        self.eta_secs = self.total_bytes.filter(|_| bytes_per_sec > 0).map(|total| {
+            total
+                .saturating_sub(self.downloaded_bytes)
+                .div_ceil(bytes_per_sec)
+        });
+    }
+}
+
+/// Payload of the `download-complete` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadComplete {
+    pub game_date: String,
+    pub path: PathBuf,
+}
+
+/// Payload of the `download-error` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadError {
+    pub game_date: String,
+    pub message: String,
+}
+
+/// Payload of the `download-cancelled` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadCancelled {
+    pub game_date: String,
+}
+
+/// Receives download lifecycle notifications
+///
+/// Implemented for `AppHandle` to forward them to the frontend as events.
+pub trait DownloadEvents: Send + Sync {
+    fn progress(&self, progress: &DownloadProgress);
+    fn complete(&self, game_date: &str, path: &Path);
+    fn error(&self, game_date: &str, message: &str);
+    fn cancelled(&self, game_date: &str);
+    fn extract_progress(&self, progress: &ExtractProgress);
+}
+
+impl<R: Runtime> DownloadEvents for AppHandle<R> {
+    fn progress(&self, progress: &DownloadProgress) {
+        let _ = self.emit("download-progress", progress.clone());
+    }
+
+    fn complete(&self, game_date: &str, path: &Path) {
+        let _ = self.emit(
+            "download-complete",
+            DownloadComplete {
+                game_date: game_date.to_string(),
+                path: path.to_path_buf(),
+            },
+        );
+    }
+
+    fn error(&self, game_date: &str, message: &str) {
+        let _ = self.emit(
+            "download-error",
+            DownloadError {
+                game_date: game_date.to_string(),
+                message: message.to_string(),
+            },
+        );
+    }
+
+    fn cancelled(&self, game_date: &str) {
+        let _ = self.emit(
+            "download-cancelled",
+            DownloadCancelled {
+                game_date: game_date.to_string(),
+            },
+        );
+    }
+
+    fn extract_progress(&self, progress: &ExtractProgress) {
+        let _ = self.emit("extract-progress", progress.clone());
+    }
+}
+
+/// Cancellation handles for downloads that are queued or running
+///
+/// Held in Tauri managed state so `cancel_download` can reach running tasks.
+#[derive(Default)]
+pub struct ActiveDownloads {
+    tokens: Mutex<HashMap<String, CancellationToken>>,
+}
+
+impl ActiveDownloads {
+    /// Token for `game_date`, shared with any download of it already in flight
+    pub fn token(&self, game_date: &str) -> CancellationToken {
+        self.tokens
+            .lock()
+            .unwrap()
+            .entry(game_date.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Register a new download of `game_date`, failing if one is queued or running
+    pub fn start(&self, game_date: &str) -> Result<CancellationToken, SdrError> {
+        match self.tokens.lock().unwrap().entry(game_date.to_string()) {
+            Entry::Occupied(_) => Err(SdrError::Invalid(format!(
+                "{game_date} is already downloading or queued"
+            ))),
+            Entry::Vacant(entry) => Ok(entry.insert(CancellationToken::new()).clone()),
+        }
+    }
+
+    /// Signal the download of `game_date` to stop; does nothing if there is none
+    pub fn cancel(&self, game_date: &str) {
+        if let Some(token) = self.tokens.lock().unwrap().get(game_date) {
+            token.cancel();
+        }
+    }
+
+    /// Whether `game_date` is queued or downloading
+    pub fn is_active(&self, game_date: &str) -> bool {
+        self.tokens.lock().unwrap().contains_key(game_date)
+    }
+
+    /// Forget `game_date` once its download has ended one way or another
+    pub fn finish(&self, game_date: &str) {
+        self.tokens.lock().unwrap().remove(game_date);
+    }
+}
+
+/// What `download_game` would do, as reported by a dry run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadPlan {
+    pub game_date: String,
+    /// Archive URL on the mirror that answered
+    pub url: String,
+    /// Size of the archive, or the catalog's size if the server didn't say
+    pub total_bytes: u64,
+    /// Bytes still to fetch, less whatever a partial download already holds
+    pub would_download_bytes: u64,
+    pub already_installed: bool,
+    pub required_bytes: u64,
+    pub available_bytes: u64,
+    pub space_ok: bool,
+}
+
+/// Decides when enough time or bytes have passed to report progress again
+struct ProgressThrottle {
+    last_at: Instant,
+    last_bytes: u64,
+}
+
+impl ProgressThrottle {
+    fn new() -> Self {
+        Self {
+            last_at: Instant::now(),
+            last_bytes: 0,
+        }
+    }
+
+    fn should_emit(&mut self, downloaded: u64) -> bool {
+        if self.last_at.elapsed() < PROGRESS_INTERVAL
+            && downloaded - self.last_bytes < PROGRESS_BYTES
+        {
+            return false;
+        }
+        self.last_at = Instant::now();
+        self.last_bytes = downloaded;
+        true
+    }
+}
+
+/// Moving average of a download's speed over the last `window`
+///
+/// Keeps one sample per progress event, so a burst or a stall of a fraction
+/// of a second barely moves the reported speed.
+pub struct SpeedMeter {
+    window: Duration,
+    /// When each sample was taken and the bytes downloaded by then, oldest first
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl SpeedMeter {
+    pub fn new(window: Duration, now: Instant, downloaded: u64) -> Self {
+        Self {
+            window,
+            samples: VecDeque::from([(now, downloaded)]),
+        }
+    }
+
+    /// Add a sample and return the average bytes per second over the window
+    pub fn record(&mut self, now: Instant, downloaded: u64) -> u64 {
+        self.samples.push_back((now, downloaded));
+        // Keep the newest sample at least a window old, as the start of the window
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|&(at, _)| now.duration_since(at) >= self.window)
+        {
+            self.samples.pop_front();
+        }
+        let (since, from) = self.samples[0];
+        let elapsed = now.duration_since(since).as_secs_f64();
+        if elapsed <= 0.0 {
+            return 0;
+        }
+        (downloaded.saturating_sub(from) as f64 / elapsed) as u64
+    }
+}
+
+/// Download and install a game into `games_dir`, reporting progress to `events`
+///
+/// Returns the directory the game was extracted into. Bytes are written to a
+/// `.part` file that is only renamed into place once the download finishes, so
+/// an interrupted download can be resumed on the next call.
+/// When the server advertises a checksum the archive is hashed as it is written
+/// and discarded if it doesn't match. Cancelling `cancel` stops the download and
+/// removes the partial file, while cancelling `pause` flushes the partial file
+/// and returns `SdrError::Paused` so a later call can resume. The verified
+/// archive is unpacked into `<games_dir>/<game_date>/` and then deleted.
+/// Progress events carry the speed averaged over `speed_window`.
+pub async fn download_game(
+    api: &ApiClient,
+    games_dir: &Path,
+    game: &Game,
+    events: &dyn DownloadEvents,
+    cancel: &CancellationToken,
+    pause: &CancellationToken,
+    speed_window: Duration,
+) -> Result<PathBuf, SdrError> {
+    paths::check_game_date(&game.date)?;
+    let result = tokio::select! {
+        biased;
+        () = cancel.cancelled() => return Err(discard_cancelled(games_dir, &game.date, events).await),
+        result = fetch_archive(api, games_dir, game, events, pause, speed_window) => result,
+    };
+    let result = match result {
+        Ok(archive) => install_archive(games_dir, game, &archive, events).await,
+        Err(message) => Err(message),
+    };
+    match result {
+        Ok(path) => {
+            events.complete(&game.date, &path);
+            Ok(path)
+        }
+        Err(error @ SdrError::Paused(_)) => Err(error),
+        Err(error) => {
+            events.error(&game.date, &error.to_string());
+            Err(error)
+        }
+    }
+}
+
+/// Remove the partial file of a cancelled download and report the cancellation
+///
+/// Returns the error the download ends with.
+pub async fn discard_cancelled(
+    games_dir: &Path,
+    game_date: &str,
+    events: &dyn DownloadEvents,
+) -> SdrError {
+    let _ = tokio::fs::remove_file(paths::part_path(games_dir, game_date)).await;
+    events.cancelled(game_date);
+    SdrError::Cancelled(format!("Download of {game_date} was cancelled"))
+}
+
+async fn fetch_archive(
+    api: &ApiClient,
+    games_dir: &Path,
+    game: &Game,
+    events: &dyn DownloadEvents,
+    pause: &CancellationToken,
+    speed_window: Duration,
+) -> Result<PathBuf, SdrError> {
+    let game_date = game.date.as_str();
+    let started = Instant::now();
+    tokio::fs::create_dir_all(games_dir)
+        .await
+        .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", games_dir.display())))?;
+    if game.size_bytes > 0 {
+        disk::ensure_space(games_dir, game.size_bytes)?;
+    }
+    let part_path = paths::part_path(games_dir, game_date);
+    let existing = tokio::fs::metadata(&part_path)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+
+    let mut response = request_archive(api, game_date, existing).await?;
+    let resume_total = resumable_total(&response, existing, game.size_bytes);
+    if existing > 0 && resume_total.is_none() && response.status() != StatusCode::OK {
+        // The server refused the range or it no longer matches, start over
+        response = request_archive(api, game_date, 0).await?;
+    }
+
+    let url = response.url().to_string();
+    let mut response = api::check_status(response, &url)?;
+
+    let mut hasher = Sha256::new();
+    let (mut file, mut progress) = match resume_total {
+        Some(total) => {
+            checksum::update_from_file(&mut hasher, &part_path).await?;
+            let file = tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(&part_path)
+                .await
+                .map_err(|e| {
+                    SdrError::Io(format!("Failed to open {}: {e}", part_path.display()))
+                })?;
+            (
+                file,
+                DownloadProgress::new(game_date, existing, Some(total)),
+            )
+        }
+        None => {
+            let file = tokio::fs::File::create(&part_path).await.map_err(|e| {
+                SdrError::Io(format!("Failed to create {}: {e}", part_path.display()))
+            })?;
+            let total = response.content_length();
+            (file, DownloadProgress::new(game_date, 0, total))
+        }
+    };
+    let mut throttle = ProgressThrottle::new();
+    let mut speed = SpeedMeter::new(speed_window, Instant::now(), progress.downloaded_bytes);
+
+    loop {
+        let chunk = tokio::select! {
+            biased;
+            () = pause.cancelled() => {
+                file.flush().await.map_err(|e| {
+                    SdrError::Io(format!("Failed to write {}: {e}", part_path.display()))
+                })?;
+                progress.set_speed(0);
+                events.progress(&progress);
+                return Err(SdrError::Paused(format!("Download of {game_date} was paused")));
+            }
+            chunk = response.chunk() => chunk.map_err(|e| {
+                api::network_error(&e, format!("Download of {game_date} interrupted: {e}"))
+            })?,
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| SdrError::Io(format!("Failed to write {}: {e}", part_path.display())))?;
+        hasher.update(&chunk);
+        progress.downloaded_bytes += chunk.len() as u64;
+        if throttle.should_emit(progress.downloaded_bytes) {
+            progress.set_speed(speed.record(Instant::now(), progress.downloaded_bytes));
+            events.progress(&progress);
+        }
+        api.throttle(chunk.len() as u64).await;
+    }
+
+    file.flush()
+        .await
+        .map_err(|e| SdrError::Io(format!("Failed to write {}: {e}", part_path.display())))?;
+    drop(file);
+    progress.set_speed(speed.record(Instant::now(), progress.downloaded_bytes));
+    events.progress(&progress);
+
+    if let Some(expected) = &game.checksum {
+        let actual = checksum::to_hex(hasher);
+        if let Err(message) = checksum::verify(game_date, expected, &actual) {
+            let _ = tokio::fs::remove_file(&part_path).await;
+            return Err(message);
+        }
+    }
+
+    let path = paths::archive_path(games_dir, game_date);
+    tokio::fs::rename(&part_path, &path).await.map_err(|e| {
+        SdrError::Io(format!(
+            "Failed to move {} into place: {e}",
+            part_path.display()
+        ))
+    })?;
+    tracing::info!(
+        bytes = progress.downloaded_bytes,
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "archive downloaded"
+    );
+    Ok(path)
+}
+
+/// Run the preflight checks of `download_game` without writing anything
+///
+/// The archive size comes from a HEAD request, or a one-byte ranged GET for
+/// servers that don't answer HEAD.
+pub async fn plan_download(
+    api: &ApiClient,
+    games_dir: &Path,
+    game: &Game,
+) -> Result<DownloadPlan, SdrError> {
+    let game_date = game.date.as_str();
+    paths::check_game_date(game_date)?;
+    let (url, size) = archive_size(api, game_date).await?;
+    let total_bytes = size.unwrap_or(game.size_bytes);
+    let existing = tokio::fs::metadata(paths::part_path(games_dir, game_date))
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0)
+        .min(total_bytes);
+
+    // The games dir may not exist yet, so ask the closest ancestor that does
+    let existing_dir = games_dir
+        .ancestors()
+        .find(|dir| dir.is_dir())
+        .unwrap_or(games_dir);
+    let available_bytes = disk::free_space(existing_dir)?;
+    let required_bytes = disk::required_space(total_bytes).saturating_sub(existing);
+    Ok(DownloadPlan {
+        game_date: game_date.to_string(),
+        url,
+        total_bytes,
+        would_download_bytes: total_bytes - existing,
+        already_installed: paths::game_dir(games_dir, game_date).is_dir(),
+        required_bytes,
+        available_bytes,
+        space_ok: available_bytes >= required_bytes,
+    })
+}
+
+/// URL and advertised size of a game's archive
+async fn archive_size(api: &ApiClient, game_date: &str) -> Result<(String, Option<u64>), SdrError> {
+    let archive_url = |base_url: &str| config::game_archive_url(base_url, game_date);
+    if let Ok(response) = api.head_raw(archive_url).await {
+        if response.status().is_success() {
+            if let Some(size) = response.content_length().filter(|&size| size > 0) {
+                return Ok((response.url().to_string(), Some(size)));
+            }
+        }
+    }
+
+    let response = api
+        .get_raw(archive_url, |request| request.header(RANGE, "bytes=0-0"))
+        .await?;
+    let url = response.url().to_string();
+    let response = api::check_status(response, &url)?;
+    let size = if response.status() == StatusCode::PARTIAL_CONTENT {
+        response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_content_range)
+            .map(|(_, total)| total)
+    } else {
+        // The range was ignored; dropping the response stops the body download
+        response.content_length()
+    };
+    Ok((url, size))
+}
+
+/// Extract a downloaded archive into the game's directory and remove the archive
+async fn install_archive(
+    games_dir: &Path,
+    game: &Game,
+    archive: &Path,
+    events: &dyn DownloadEvents,
+) -> Result<PathBuf, SdrError> {
+    let game_date = game.date.as_str();
+    let target = paths::game_dir(games_dir, game_date);
+    let staging = paths::staging_dir(games_dir, game_date);
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let task = {
+        let archive = archive.to_path_buf();
+        let target = target.clone();
+        let game = game.clone();
+        tokio::task::spawn_blocking(move || {
+            extract::extract_archive(&archive, &staging, &target, |extracted, total| {
+                let _ = tx.send((extracted, total));
+            })?;
+            if let Err(e) = install::record_install(&target, &game) {
+                tracing::warn!("couldn't record the installed build of {}: {e}", game.date);
+            }
+            Ok::<(), SdrError>(())
+        })
+    };
+
+    while let Some((extracted_entries, total_entries)) = rx.recv().await {
+        events.extract_progress(&ExtractProgress {
+            game_date: game_date.to_string(),
+            extracted_entries,
+            total_entries,
+        });
+    }
+    task.await
+        .map_err(|e| SdrError::Internal(format!("Extraction of {game_date} failed: {e}")))??;
+
+    tokio::fs::remove_file(archive)
+        .await
+        .map_err(|e| SdrError::Io(format!("Failed to remove {}: {e}", archive.display())))?;
+    Ok(target)
+}
+
+/// GET the archive, asking for only the bytes after `offset` when resuming
+async fn request_archive(
+    api: &ApiClient,
+    game_date: &str,
+    offset: u64,
+) -> Result<reqwest::Response, SdrError> {
+    api.get_raw(
+        |base_url| config::game_archive_url(base_url, game_date),
+        |request| {
+            if offset > 0 {
+                request.header(RANGE, format!("bytes={offset}-"))
+            } else {
+                request
+            }
+        },
+    )
+    .await
+}
+
+/// If `response` continues a partial file of `existing` bytes, the full archive size
+///
+/// Returns `None` when the download has to restart: the server ignored the range,
+/// resumed from the wrong offset, or reports a size that differs from the manifest.
+fn resumable_total(response: &reqwest::Response, existing: u64, expected_size: u64) -> Option<u64> {
+    if existing == 0 || response.status() != StatusCode::PARTIAL_CONTENT {
+        return None;
+    }
+    let range = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, total) = parse_content_range(range)?;
+    if start != existing || (expected_size > 0 && total != expected_size) {
+        return None;
+    }
+    Some(total)
+}
+
+/// Parse `bytes <start>-<end>/<total>` into the start offset and total size
+fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _end) = range.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use crate::test_support::{self, MockServer, RecordingEvents, Response};
+
+    /// Serves `archive` for every path, honouring `Range: bytes=<start>-`
+    async fn archive_server(archive: Vec<u8>) -> MockServer {
+        MockServer::start(move |request| test_support::archive_response(&archive, request)).await
+    }
+
+    fn sample_game(archive: &[u8]) -> Game {
+        Game {
+            size_bytes: archive.len() as u64,
+            checksum: Some(format!("{:x}", Sha256::digest(archive))),
+            ..test_support::game("g1", "2024-03-01")
+        }
+    }
+
+    async fn download(
+        api: &ApiClient,
+        games_dir: &Path,
+        game: &Game,
+        events: &RecordingEvents,
+    ) -> Result<PathBuf, SdrError> {
+        download_game(
+            api,
+            games_dir,
+            game,
+            events,
+            &CancellationToken::new(),
+            &CancellationToken::new(),
+            Duration::from_secs(DEFAULT_SPEED_WINDOW_SECS),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn progress_is_monotonic_and_reaches_the_total() {
+        let archive = test_support::sample_archive();
+        let server = archive_server(archive.clone()).await;
+        let api = ApiClient::new(ServerConfig::new([&server.url]));
+        let games_dir = tempfile::tempdir().unwrap();
+        let game = sample_game(&archive);
+        let events = RecordingEvents::default();
+
+        let path = download(&api, games_dir.path(), &game, &events)
+            .await
+            .unwrap();
+
+        let progress = events.progress.lock().unwrap().clone();
+        assert!(progress.len() > 1, "expected several progress events");
+        assert!(progress
+            .windows(2)
+            .all(|pair| pair[0].downloaded_bytes <= pair[1].downloaded_bytes));
+        let last = progress.last().unwrap();
+        assert_eq!(last.downloaded_bytes, archive.len() as u64);
+        assert_eq!(last.total_bytes, Some(archive.len() as u64));
+        assert_eq!(*events.completed.lock().unwrap(), vec![game.date.clone()]);
+        assert!(path.join("game.bin").is_file());
+        assert!(!paths::part_path(games_dir.path(), &game.date).exists());
+    }
+
+    #[tokio::test]
+    async fn resumes_a_partial_download_with_a_range_request() {
+        let archive = test_support::sample_archive();
+        let server = archive_server(archive.clone()).await;
+        let api = ApiClient::new(ServerConfig::new([&server.url]));
+        let games_dir = tempfile::tempdir().unwrap();
+        let game = sample_game(&archive);
+        let half = archive.len() / 2;
+        std::fs::write(
+            paths::part_path(games_dir.path(), &game.date),
+            &archive[..half],
+        )
+        .unwrap();
+        let events = RecordingEvents::default();
+
+        let path = download(&api, games_dir.path(), &game, &events)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].header("Range"),
+            Some(format!("bytes={half}-").as_str())
+        );
+        let progress = events.progress.lock().unwrap().clone();
+        assert!(progress
+            .iter()
+            .all(|progress| progress.downloaded_bytes >= half as u64));
+        // The checksum covers both halves, so a bad splice would have failed here
+        assert!(path.join("game.bin").is_file());
+    }
+
+    #[test]
+    fn content_range_is_parsed() {
+        assert_eq!(parse_content_range("bytes 100-199/200"), Some((100, 200)));
+        assert_eq!(parse_content_range("bytes */200"), None);
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_deletes_the_download() {
+        let archive = test_support::sample_archive();
+        let server = archive_server(archive.clone()).await;
+        let api = ApiClient::new(ServerConfig::new([&server.url]));
+        let games_dir = tempfile::tempdir().unwrap();
+        let game = Game {
+            checksum: Some("0".repeat(64)),
+            ..sample_game(&archive)
+        };
+        let events = RecordingEvents::default();
+
+        let error = download(&api, games_dir.path(), &game, &events)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), "ChecksumMismatch");
+        assert_eq!(events.errors.lock().unwrap().len(), 1);
+        assert!(!paths::part_path(games_dir.path(), &game.date).exists());
+        assert!(!paths::archive_path(games_dir.path(), &game.date).exists());
+        assert!(!paths::game_dir(games_dir.path(), &game.date).exists());
+    }
+
+    #[tokio::test]
+    async fn cancelling_midway_removes_the_partial_file() {
+        let archive = test_support::sample_archive();
+        let server = MockServer::start(move |_| {
+            Response::new(200)
+                .body(archive.clone())
+                .slow(64 * 1024, Duration::from_millis(20))
+        })
+        .await;
+        let api = ApiClient::new(ServerConfig::new([&server.url]));
+        let games_dir = tempfile::tempdir().unwrap();
+        let game = test_support::game("g1", "2024-03-01");
+        let events = RecordingEvents::default();
+        let (cancel, pause) = (CancellationToken::new(), CancellationToken::new());
+
+        let download = download_game(
+            &api,
+            games_dir.path(),
+            &game,
+            &events,
+            &cancel,
+            &pause,
+            Duration::from_secs(DEFAULT_SPEED_WINDOW_SECS),
+        );
+        let cancel_midway = async {
+            let part_path = paths::part_path(games_dir.path(), &game.date);
+            while std::fs::metadata(&part_path).map_or(0, |m| m.len()) == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            cancel.cancel();
+        };
+        let (result, ()) = tokio::join!(download, cancel_midway);
+
+        assert_eq!(result.unwrap_err().kind(), "Cancelled");
+        assert_eq!(*events.cancelled.lock().unwrap(), vec![game.date.clone()]);
+        assert!(!paths::part_path(games_dir.path(), &game.date).exists());
+        assert!(!paths::game_dir(games_dir.path(), &game.date).exists());
+    }
+
+    #[test]
+    fn a_second_download_of_the_same_game_is_rejected() {
+        let active = ActiveDownloads::default();
+        let token = active.start("2024-03-01").unwrap();
+        assert_eq!(active.start("2024-03-01").unwrap_err().kind(), "Invalid");
+        // Jobs of the registered download share its token
+        active.cancel("2024-03-01");
+        assert!(token.is_cancelled());
+
+        active.finish("2024-03-01");
+        assert!(active.start("2024-03-01").is_ok());
+    }
+
+    #[tokio::test]
+    async fn a_paused_download_keeps_its_part_file_and_resumes() {
+        let archive = test_support::sample_archive();
+        let served = archive.clone();
+        let server = MockServer::start(move |request| {
+            test_support::archive_response(&served, request)
+                .slow(64 * 1024, Duration::from_millis(20))
+        })
+        .await;
+        let api = ApiClient::new(ServerConfig::new([&server.url]));
+        let games_dir = tempfile::tempdir().unwrap();
+        let game = sample_game(&archive);
+        let part_path = paths::part_path(games_dir.path(), &game.date);
+        let events = RecordingEvents::default();
+        let (cancel, pause) = (CancellationToken::new(), CancellationToken::new());
+
+        let paused = download_game(
+            &api,
+            games_dir.path(),
+            &game,
+            &events,
+            &cancel,
+            &pause,
+            Duration::from_secs(DEFAULT_SPEED_WINDOW_SECS),
+        );
+        let pause_midway = async {
+            while std::fs::metadata(&part_path).map_or(0, |m| m.len()) == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            pause.cancel();
+        };
+        let (result, ()) = tokio::join!(paused, pause_midway);
+
+        assert_eq!(result.unwrap_err().kind(), "Paused");
+        assert!(events.errors.lock().unwrap().is_empty());
+        let kept = std::fs::metadata(&part_path).unwrap().len();
+        assert!(kept > 0 && kept < archive.len() as u64, "kept {kept} bytes");
+
+        let path = download(&api, games_dir.path(), &game, &events)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(
+            requests.last().unwrap().header("Range"),
+            Some(format!("bytes={kept}-").as_str())
+        );
+        assert!(path.join("game.bin").is_file());
+        assert!(!part_path.exists());
+        assert_eq!(*events.completed.lock().unwrap(), vec![game.date.clone()]);
+    }
+
+    #[tokio::test]
+    async fn a_dry_run_touches_no_files() {
+        let archive = test_support::sample_archive();
+        let server = archive_server(archive.clone()).await;
+        let api = ApiClient::new(ServerConfig::new([&server.url]));
+        let parent = tempfile::tempdir().unwrap();
+        let games_dir = parent.path().join("games");
+        let game = sample_game(&archive);
+
+        let plan = plan_download(&api, &games_dir, &game).await.unwrap();
+
+        assert_eq!(plan.total_bytes, archive.len() as u64);
+        assert_eq!(plan.would_download_bytes, archive.len() as u64);
+        assert!(!plan.already_installed);
+        assert!(!games_dir.exists());
+        assert_eq!(std::fs::read_dir(parent.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn the_speed_is_averaged_over_the_window() {
+        let start = Instant::now();
+        let at = |millis: u64| start + Duration::from_millis(millis);
+        let mut meter = SpeedMeter::new(Duration::from_secs(5), start, 0);
+
+        // 1 MB/s for ten seconds, sampled twice a second
+        let mut speed = 0;
+        for tick in 1..=20 {
+            speed = meter.record(at(tick * 500), tick * 500_000);
+        }
+        assert!((990_000..=1_010_000).contains(&speed), "{speed}");
+
+        // A one second burst at 6 MB/s only nudges the average
+        let burst = meter.record(at(11_000), 16_000_000);
+        assert!((1_500_000..=3_000_000).contains(&burst), "{burst}");
+
+        // Once the window has passed without data, the speed drops to zero
+        meter.record(at(14_000), 16_000_000);
+        assert_eq!(meter.record(at(17_000), 16_000_000), 0);
+    }
+
+    #[test]
+    fn the_eta_follows_the_speed() {
+        let mut progress = DownloadProgress::new("2024-03-01", 4_000_000, Some(10_000_000));
+
+        progress.set_speed(1_000_000);
+        assert_eq!(progress.eta_secs, Some(6));
+        progress.set_speed(4_000_000);
+        assert_eq!(progress.eta_secs, Some(2));
+        progress.set_speed(0);
+        assert_eq!(progress.eta_secs, None);
+
+        let mut unknown_size = DownloadProgress::new("2024-03-01", 4_000_000, None);
+        unknown_size.set_speed(1_000_000);
+        assert_eq!(unknown_size.eta_secs, None);
+    }
+}
@@ -0,0 +1,224 @@
+// Error type returned by every loader command
+
+use std::path::PathBuf;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+const MIB: u64 = 1024 * 1024;
+
+/// Everything that can go wrong in the loader
+///
+/// Serialized to the frontend as `{ kind, message }`, where `kind` is the
+/// variant name, so the UI can react to specific failures.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SdrError {
+    /// The server couldn't be reached or the connection dropped
+    #[error("{0}")]
+    Network(String),
+    /// The server took longer to connect or answer than the configured timeout
+    #[error("{0}")]
+    Timeout(String),
+    /// The server answered with an unexpected status
+    #[error("{message}")]
+    Http { status: u16, message: String },
+    /// The server's TLS certificate doesn't match the configured pin
+    #[error("{0}")]
+    CertPinMismatch(String),
+    /// The loader's own request cap was hit and the wait for a turn ran out
+    #[error("{0}")]
+    RateLimited(String),
+    /// The server rejected the API key, or one is required
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Io(String),
+    /// Data from the server or disk couldn't be decoded
+    #[error("{0}")]
+    Parse(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error(
+        "Insufficient space in {}: {} MiB needed, {} MiB free",
+        .path.display(),
+        .required.div_ceil(MIB),
+        .available / MIB
+    )]
+    InsufficientSpace {
+        path: PathBuf,
+        required: u64,
+        available: u64,
+    },
+    #[error("Checksum mismatch for {game_date}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        game_date: String,
+        expected: String,
+        actual: String,
+    },
+    /// A request the loader refuses to carry out, like an unsafe path
+    #[error("{0}")]
+    Invalid(String),
+    #[error("{0}")]
+    Cancelled(String),
+    /// The download queue was paused; the partial download is kept
+    #[error("{0}")]
+    Paused(String),
+    /// A background task failed unexpectedly
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl SdrError {
+    /// Machine-readable name of the variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SdrError::Network(_) => "Network",
+            SdrError::Timeout(_) => "Timeout",
+            SdrError::Http { .. } => "Http",
+            SdrError::CertPinMismatch(_) => "CertPinMismatch",
+            SdrError::RateLimited(_) => "RateLimited",
+            SdrError::Unauthorized(_) => "Unauthorized",
+            SdrError::Io(_) => "Io",
+            SdrError::Parse(_) => "Parse",
+            SdrError::NotFound(_) => "NotFound",
+            SdrError::InsufficientSpace { .. } => "InsufficientSpace",
+            SdrError::ChecksumMismatch { .. } => "ChecksumMismatch",
+            SdrError::Invalid(_) => "Invalid",
+            SdrError::Cancelled(_) => "Cancelled",
+            SdrError::Paused(_) => "Paused",
+            SdrError::Internal(_) => "Internal",
+        }
+    }
+
+    /// Whether retrying the same request could succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            SdrError::Network(_) | SdrError::Timeout(_) => true,
+            SdrError::Http { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Note how many attempts were made before giving up
+    pub fn after_attempts(self, attempts: u32) -> Self {
+        let plural = if attempts == 1 { "" } else { "s" };
+        let suffix = format!(" (after {attempts} attempt{plural})");
+        match self {
+            SdrError::Network(message) => SdrError::Network(message + &suffix),
+            SdrError::Timeout(message) => SdrError::Timeout(message + &suffix),
+            SdrError::Http { status, message } => SdrError::Http {
+                status,
+                message: message + &suffix,
+            },
+            SdrError::Parse(message) => SdrError::Parse(message + &suffix),
+            other => other,
+        }
+    }
+}
+
+impl Serialize for SdrError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SdrError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One of every variant, with the kind each must report
+    fn every_variant() -> Vec<(SdrError, &'static str)> {
+        let text = || String::from("boom");
+        vec![
+            (SdrError::Network(text()), "Network"),
+            (SdrError::Timeout(text()), "Timeout"),
+            (
+                SdrError::Http {
+                    status: 502,
+                    message: text(),
+                },
+                "Http",
+            ),
+            (SdrError::CertPinMismatch(text()), "CertPinMismatch"),
+            (SdrError::RateLimited(text()), "RateLimited"),
+            (SdrError::Unauthorized(text()), "Unauthorized"),
+            (SdrError::Io(text()), "Io"),
+            (SdrError::Parse(text()), "Parse"),
+            (SdrError::NotFound(text()), "NotFound"),
+            (
+                SdrError::InsufficientSpace {
+                    path: PathBuf::from("/games"),
+                    required: 3 * MIB,
+                    available: MIB,
+                },
+                "InsufficientSpace",
+            ),
+            (
+                SdrError::ChecksumMismatch {
+                    game_date: String::from("2024-01-01"),
+                    expected: String::from("aa"),
+                    actual: String::from("bb"),
+                },
+                "ChecksumMismatch",
+            ),
+            (SdrError::Invalid(text()), "Invalid"),
+            (SdrError::Cancelled(text()), "Cancelled"),
+            (SdrError::Paused(text()), "Paused"),
+            (SdrError::Internal(text()), "Internal"),
+        ]
+    }
+
+    #[test]
+    fn every_variant_serializes_as_kind_and_message() {
+        for (error, kind) in every_variant() {
+            assert_eq!(error.kind(), kind);
+            let json = serde_json::to_value(&error).unwrap();
+            assert_eq!(
+                json,
+                serde_json::json!({ "kind": kind, "message": error.to_string() })
+            );
+        }
+    }
+
+    #[test]
+    fn structured_variants_have_readable_messages() {
+        let messages: Vec<String> = every_variant()
+            .into_iter()
+            .filter(|(_, kind)| matches!(*kind, "InsufficientSpace" | "ChecksumMismatch"))
+            .map(|(error, _)| error.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "Insufficient space in /games: 3 MiB needed, 1 MiB free",
+                "Checksum mismatch for 2024-01-01: expected aa, got bb",
+            ]
+        );
+    }
+
+    #[test]
+    fn only_network_failures_and_server_errors_are_transient() {
+        let transient: Vec<&str> = every_variant()
+            .into_iter()
+            .filter(|(error, _)| error.is_transient())
+            .map(|(_, kind)| kind)
+            .collect();
+        assert_eq!(transient, ["Network", "Timeout", "Http"]);
+        let client_error = SdrError::Http {
+            status: 404,
+            message: String::from("missing"),
+        };
+        assert!(!client_error.is_transient());
+    }
+
+    #[test]
+    fn after_attempts_notes_the_count() {
+        let error = SdrError::Network(String::from("refused")).after_attempts(3);
+        assert_eq!(error.to_string(), "refused (after 3 attempts)");
+        let error = SdrError::Invalid(String::from("bad id")).after_attempts(3);
+        assert_eq!(error.to_string(), "bad id");
+    }
+}
@@ -0,0 +1,162 @@
+// Unpacking downloaded game archives
+
+use std::fs::File;
+use std::path::Path;
+
+use serde::Serialize;
+use zip::ZipArchive;
+
+use crate::error::SdrError;
+
+/// Payload of the `extract-progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractProgress {
+    pub game_date: String,
+    pub extracted_entries: usize,
+    pub total_entries: usize,
+}
+
+/// Unpack `archive` into `target`, replacing whatever was there
+///
+/// Entries are written to `staging` first and only moved into place once the
+/// whole archive extracted cleanly. Any entry whose path would escape the target
+/// directory (zip-slip) aborts the extraction.
+pub fn extract_archive(
+    archive: &Path,
+    staging: &Path,
+    target: &Path,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Result<(), SdrError> {
+    let file = File::open(archive)
+        .map_err(|e| SdrError::Io(format!("Failed to open {}: {e}", archive.display())))?;
+    let mut zip = ZipArchive::new(file)
+        .map_err(|e| SdrError::Parse(format!("Invalid game archive {}: {e}", archive.display())))?;
+
+    let _ = std::fs::remove_dir_all(staging);
+    let result = unpack(&mut zip, staging, &mut on_progress);
+    if let Err(e) = result {
+        let _ = std::fs::remove_dir_all(staging);
+        return Err(e);
+    }
+
+    if target.exists() {
+        std::fs::remove_dir_all(target)
+            .map_err(|e| SdrError::Io(format!("Failed to replace {}: {e}", target.display())))?;
+    }
+    std::fs::rename(staging, target).map_err(|e| {
+        SdrError::Io(format!(
+            "Failed to move game into {}: {e}",
+            target.display()
+        ))
+    })
+}
+
+fn unpack(
+    zip: &mut ZipArchive<File>,
+    dest: &Path,
+    on_progress: &mut impl FnMut(usize, usize),
+) -> Result<(), SdrError> {
+    std::fs::create_dir_all(dest)
+        .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", dest.display())))?;
+    let total = zip.len();
+    let step = (total / 100).max(1);
+
+    for index in 0..total {
+        let mut entry = zip
+            .by_index(index)
+            .map_err(|e| SdrError::Parse(format!("Failed to read archive entry {index}: {e}")))?;
+        let relative = entry.enclosed_name().ok_or_else(|| {
+            SdrError::Invalid(format!(
+                "Archive entry {:?} escapes the game directory",
+                entry.name()
+            ))
+        })?;
+        let path = dest.join(relative);
+
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)
+                .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", path.display())))?;
+        } else {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    SdrError::Io(format!("Failed to create {}: {e}", parent.display()))
+                })?;
+            }
+            let mut out = File::create(&path)
+                .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", path.display())))?;
+            std::io::copy(&mut entry, &mut out)
+                .map_err(|e| SdrError::Io(format!("Failed to extract {}: {e}", path.display())))?;
+            // Only the permission bits, an archive doesn't get to set setuid or sticky
+            #[cfg(unix)]
+            if let Some(mode) = entry.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                let _ =
+                    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode & 0o777));
+            }
+        }
+
+        let extracted = index + 1;
+        if extracted % step == 0 || extracted == total {
+            on_progress(extracted, total);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    /// Write `files` as an archive into `dir`, returning its path
+    fn write_archive(dir: &Path, files: &[(&str, &[u8])]) -> std::path::PathBuf {
+        let path = dir.join("game.zip");
+        std::fs::write(&path, test_support::zip_archive(files)).unwrap();
+        path
+    }
+
+    #[test]
+    fn extracts_every_entry_into_the_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = write_archive(
+            dir.path(),
+            &[("index.html", b"<html>"), ("client/game.js", b"run()")],
+        );
+        let (staging, target) = (dir.path().join(".staging"), dir.path().join("game"));
+        let mut progress = Vec::new();
+
+        extract_archive(&archive, &staging, &target, |done, total| {
+            progress.push((done, total))
+        })
+        .unwrap();
+
+        assert_eq!(std::fs::read(target.join("index.html")).unwrap(), b"<html>");
+        assert_eq!(
+            std::fs::read(target.join("client/game.js")).unwrap(),
+            b"run()"
+        );
+        assert!(!staging.exists());
+        assert_eq!(progress.last(), Some(&(2, 2)));
+    }
+
+    #[test]
+    fn an_entry_escaping_the_target_aborts_the_extraction() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = write_archive(
+            dir.path(),
+            &[("index.html", b"<html>"), ("../escaped.txt", b"gotcha")],
+        );
+        let (staging, target) = (dir.path().join(".staging"), dir.path().join("game"));
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(target.join("old.txt"), b"previous install").unwrap();
+
+        let error = extract_archive(&archive, &staging, &target, |_, _| {}).unwrap_err();
+
+        assert_eq!(error.kind(), "Invalid", "{error}");
+        assert!(!dir.path().join("escaped.txt").exists());
+        assert!(!staging.exists());
+        // The previous install is left untouched
+        assert!(target.join("old.txt").is_file());
+    }
+}
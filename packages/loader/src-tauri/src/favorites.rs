@@ -0,0 +1,118 @@
+// Games starred by the player
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SdrError;
+use crate::models::Game;
+use crate::paths;
+use crate::persist;
+
+/// File name of the favorites inside the app config dir
+pub const FAVORITES_FILE: &str = "favorites.json";
+
+/// Starred game dates, as stored in `favorites.json`
+///
+/// Dates the catalog doesn't list are kept, since the game may be published later.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Favorites {
+    pub game_dates: BTreeSet<String>,
+}
+
+impl Favorites {
+    /// Default location of the favorites
+    pub fn path() -> Result<PathBuf, SdrError> {
+        Ok(paths::config_dir()?.join(FAVORITES_FILE))
+    }
+
+    /// Read the favorites at `path`, starting empty if they're missing or unreadable
+    pub fn load(path: &Path) -> Self {
+        persist::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SdrError> {
+        persist::save_json(path, self)
+    }
+
+    /// Apply `change` to the favorites at `path` and save them, one update at a time
+    pub fn update<R>(path: &Path, change: impl FnOnce(&mut Self) -> R) -> Result<R, SdrError> {
+        persist::update_json(path, change)
+    }
+
+    /// Star `game_date`, returning `false` if it already was
+    pub fn add(&mut self, game_date: &str) -> bool {
+        self.game_dates.insert(game_date.to_string())
+    }
+
+    /// Unstar `game_date`, returning `false` if it wasn't starred
+    pub fn remove(&mut self, game_date: &str) -> bool {
+        self.game_dates.remove(game_date)
+    }
+
+    pub fn contains(&self, game_date: &str) -> bool {
+        self.game_dates.contains(game_date)
+    }
+
+    /// The games in `pool` that are starred
+    pub fn filter(&self, pool: Vec<Game>) -> Vec<Game> {
+        pool.into_iter()
+            .filter(|game| self.contains(&game.date))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::randomizer::{self, WeightMode};
+    use crate::stats::Stats;
+    use crate::test_support::game;
+
+    #[test]
+    fn favorites_survive_a_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(FAVORITES_FILE);
+        let mut favorites = Favorites::load(&path);
+        assert!(favorites.add("2024-01-02"));
+        assert!(favorites.add("2024-01-01"));
+        assert!(!favorites.add("2024-01-01"));
+        favorites.save(&path).unwrap();
+
+        let mut reloaded = Favorites::load(&path);
+        assert_eq!(reloaded, favorites);
+        assert!(reloaded.remove("2024-01-02"));
+        reloaded.save(&path).unwrap();
+
+        let dates: Vec<_> = Favorites::load(&path).game_dates.into_iter().collect();
+        assert_eq!(dates, ["2024-01-01"]);
+    }
+
+    #[test]
+    fn favorites_only_picks_starred_games() {
+        let pool: Vec<_> = (1..=10)
+            .map(|day| game(&format!("g{day}"), &format!("2024-01-{day:02}")))
+            .collect();
+        let mut favorites = Favorites::default();
+        favorites.add("2024-01-03");
+        favorites.add("2024-01-07");
+        // Not in the catalog, so never picked, but still kept
+        favorites.add("2030-01-01");
+
+        let starred = favorites.filter(pool);
+
+        assert_eq!(starred.len(), 2);
+        for seed in 0..50 {
+            let pick = randomizer::pick_random_game(
+                &starred,
+                Some(seed),
+                WeightMode::Uniform,
+                &Stats::default(),
+            )
+            .unwrap();
+            assert!(favorites.contains(&pick.date), "picked {}", pick.date);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// Diagnostics of the server connection and local setup
+
+use std::time::Duration;
+
+use reqwest::header::HeaderValue;
+use serde::Serialize;
+
+use crate::api::{self, ApiClient};
+use crate::config::Config;
+use crate::disk;
+use crate::error::SdrError;
+use crate::paths;
+
+/// How long the server gets to answer the reachability check
+pub const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of one check of `health_check`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "message")]
+pub enum Check {
+    Ok,
+    Error(String),
+}
+
+impl From<Result<(), SdrError>> for Check {
+    fn from(result: Result<(), SdrError>) -> Self {
+        match result {
+            Ok(()) => Check::Ok,
+            Err(e) => Check::Error(e.to_string()),
+        }
+    }
+}
+
+/// Result of `health_check`, one entry per check
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    /// A mirror answered the health endpoint within `HEALTH_TIMEOUT`
+    pub server: Check,
+    /// The configured games directory, or the default one, exists and is writable
+    pub games_dir: Check,
+    /// At least `disk::SAFETY_MARGIN_BYTES` are free for the games directory
+    pub free_space: Check,
+    pub free_bytes: Option<u64>,
+    /// The API key, if set, can be sent in a header
+    pub api_key: Check,
+    /// The proxy URL, if set, is one the loader can use
+    pub proxy: Check,
+}
+
+/// Run every check, each reporting its own failure instead of stopping the rest
+pub async fn health_check(api: &ApiClient, config: &Config) -> HealthReport {
+    let server = api.ping(HEALTH_TIMEOUT).await.into();
+
+    // Not `paths::games_dir`, which quietly falls back to the default directory
+    let (games_dir, free_bytes) = match config
+        .games_dir
+        .clone()
+        .map_or_else(paths::default_games_dir, Ok)
+    {
+        Ok(dir) => {
+            let games_dir = match paths::ensure_writable(&dir) {
+                Ok(()) => Check::Ok,
+                Err(e) if config.games_dir.is_some() => Check::Error(format!(
+                    "{e}, so games go to the default games directory instead"
+                )),
+                Err(e) => Check::Error(e.to_string()),
+            };
+            let existing = dir.ancestors().find(|path| path.exists()).unwrap_or(&dir);
+            (games_dir, disk::free_space(existing))
+        }
+        Err(e) => (Check::Error(e.to_string()), Err(e)),
+    };
+    let free_space = match &free_bytes {
+        Ok(free) if *free < disk::SAFETY_MARGIN_BYTES => Check::Error(format!(
+            "Only {} MiB free, at least {} MiB are needed",
+            free / (1024 * 1024),
+            disk::SAFETY_MARGIN_BYTES / (1024 * 1024)
+        )),
+        Ok(_) => Check::Ok,
+        Err(e) => Check::Error(e.to_string()),
+    };
+
+    HealthReport {
+        server,
+        games_dir,
+        free_space,
+        free_bytes: free_bytes.ok(),
+        api_key: check_api_key(config.api_key.as_deref()).into(),
+        proxy: match config.proxy_url.as_deref().map(str::trim) {
+            Some(url) if !url.is_empty() => api::parse_proxy(url).map(drop).into(),
+            _ => Check::Ok,
+        },
+    }
+}
+
+/// Fail if `api_key` can't be sent as a bearer token
+fn check_api_key(api_key: Option<&str>) -> Result<(), SdrError> {
+    let Some(api_key) = api_key.map(str::trim).filter(|key| !key.is_empty()) else {
+        return Ok(());
+    };
+    HeaderValue::from_str(&format!("Bearer {api_key}"))
+        .map(drop)
+        .map_err(|_| SdrError::Invalid(String::from("API key contains invalid characters")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use crate::test_support::{self, MockServer, Response};
+
+    #[tokio::test]
+    async fn the_server_check_pings_the_health_endpoint() {
+        let server = MockServer::start(|request| match request.path() {
+            "/api/health" => Response::json(r#"{"status":"ok"}"#),
+            _ => Response::new(404),
+        })
+        .await;
+        let api = ApiClient::new(ServerConfig::new([&server.url]));
+
+        let report = health_check(&api, &Config::default()).await;
+
+        assert_eq!(report.server, Check::Ok);
+        assert_eq!(server.requests()[0].path(), "/api/health");
+    }
+
+    #[tokio::test]
+    async fn every_failing_check_is_reported_without_stopping_the_rest() {
+        let unreachable = test_support::closed_url().await;
+        let api = ApiClient::new(ServerConfig::new([&unreachable]));
+        let parent = tempfile::tempdir().unwrap();
+        // Running as root ignores permissions, so a missing dir stands in for an unwritable one
+        let config = Config {
+            games_dir: Some(parent.path().join("unmounted")),
+            ..Config::default()
+        };
+
+        let report = health_check(&api, &config).await;
+
+        assert!(
+            matches!(report.server, Check::Error(_)),
+            "{:?}",
+            report.server
+        );
+        let Check::Error(message) = &report.games_dir else {
+            panic!("the missing games dir passed: {:?}", report.games_dir);
+        };
+        assert!(message.contains("default games directory"), "{message}");
+        assert!(!parent.path().join("unmounted").exists());
+        assert_eq!(report.free_space, Check::Ok);
+        assert!(report.free_bytes.is_some());
+        assert_eq!(report.api_key, Check::Ok);
+        assert_eq!(report.proxy, Check::Ok);
+    }
+}
@@ -0,0 +1,302 @@
+// Adopting game folders that already exist on disk
+
+use std::path::Path;
+
+use crate::error::SdrError;
+use crate::install::{self, MANIFEST_FILE};
+use crate::models::{InstalledGame, Manifest};
+use crate::paths;
+use crate::persist;
+
+/// Copy, or move when `move_files` is set, the folder `source` in as game `game_date`
+///
+/// An existing install of `game_date` is only replaced when `overwrite` is set.
+/// The files land in a staging folder first, and the previous install is only
+/// moved aside until the new one is in place, so a failed import leaves it
+/// where it was, and a moved folder is put back where it came from.
+/// A folder without a `manifest.json` gets a minimal one, which still needs an
+/// executable before the game can launch.
+pub fn import_game(
+    games_dir: &Path,
+    source: &Path,
+    game_date: &str,
+    move_files: bool,
+    overwrite: bool,
+) -> Result<InstalledGame, SdrError> {
+    paths::check_game_date(game_date)?;
+    let metadata = std::fs::metadata(source)
+        .map_err(|e| SdrError::NotFound(format!("Failed to read {}: {e}", source.display())))?;
+    if !metadata.is_dir() {
+        return Err(SdrError::Invalid(format!(
+            "{} is not a directory",
+            source.display()
+        )));
+    }
+
+    let target = paths::game_dir(games_dir, game_date);
+    if target.exists() && !overwrite {
+        return Err(SdrError::Invalid(format!(
+            "{game_date} is already installed, pass overwrite to replace it"
+        )));
+    }
+    std::fs::create_dir_all(games_dir)
+        .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", games_dir.display())))?;
+    let real_source = source
+        .canonicalize()
+        .map_err(|e| SdrError::Io(format!("Failed to resolve {}: {e}", source.display())))?;
+    let real_games_dir = games_dir
+        .canonicalize()
+        .map_err(|e| SdrError::Io(format!("Failed to resolve {}: {e}", games_dir.display())))?;
+    if real_games_dir.starts_with(&real_source) || real_source.starts_with(&real_games_dir) {
+        return Err(SdrError::Invalid(format!(
+            "Can't import {}: it overlaps the games directory",
+            source.display()
+        )));
+    }
+
+    let staging = paths::import_staging_dir(games_dir, game_date);
+    let previous = paths::replaced_install_dir(games_dir, game_date);
+    if let Some(leftover) = [&staging, &previous].into_iter().find(|dir| dir.exists()) {
+        // Left by an import that never finished, and possibly the only copy
+        return Err(SdrError::Invalid(format!(
+            "An earlier import of {game_date} was interrupted, move its files out of {} first",
+            leftover.display()
+        )));
+    }
+    // Renaming only works within one filesystem, copy and delete otherwise
+    let moved = move_files && std::fs::rename(&real_source, &staging).is_ok();
+    let mut wrote_manifest = false;
+    let result = (|| {
+        if !moved {
+            copy_dir(&real_source, &staging)?;
+        }
+        wrote_manifest = ensure_manifest(&staging, &real_source)?;
+        replace_install(&staging, &target, &previous)
+    })();
+    if let Err(e) = result {
+        if moved {
+            restore_source(&staging, &real_source, wrote_manifest);
+        } else {
+            let _ = std::fs::remove_dir_all(&staging);
+        }
+        return Err(e);
+    }
+    if move_files && !moved {
+        std::fs::remove_dir_all(&real_source).map_err(|e| {
+            SdrError::Io(format!(
+                "Imported {game_date}, but failed to remove {}: {e}",
+                real_source.display()
+            ))
+        })?;
+    }
+
+    install::read_installed_game(&target, game_date.to_string()).ok_or_else(|| {
+        SdrError::Internal(format!("Imported {game_date}, but couldn't read it back"))
+    })
+}
+
+/// Move `staging` in as `target`, keeping any install already there at `previous`
+/// until that worked
+///
+/// The old install goes back to `target` if the new one can't be moved in.
+fn replace_install(staging: &Path, target: &Path, previous: &Path) -> Result<(), SdrError> {
+    let replacing = target.exists();
+    if replacing {
+        if !target.is_dir() {
+            return Err(SdrError::Io(format!(
+                "Failed to replace {}: not a folder",
+                target.display()
+            )));
+        }
+        std::fs::rename(target, previous)
+            .map_err(|e| SdrError::Io(format!("Failed to replace {}: {e}", target.display())))?;
+    }
+    if let Err(e) = std::fs::rename(staging, target) {
+        if replacing {
+            if let Err(restore) = std::fs::rename(previous, target) {
+                tracing::error!(
+                    "couldn't move {} back to {}, the old install is still there: {restore}",
+                    previous.display(),
+                    target.display()
+                );
+            }
+        }
+        return Err(SdrError::Io(format!(
+            "Failed to move game into {}: {e}",
+            target.display()
+        )));
+    }
+    if replacing {
+        if let Err(e) = std::fs::remove_dir_all(previous) {
+            tracing::warn!(
+                "couldn't remove the replaced install {}: {e}",
+                previous.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Put a folder moved into `staging` back at `source` after a failed import
+///
+/// Removes the manifest the import added, if it did. Should that fail too the
+/// files stay in `staging`, which the next import refuses to touch.
+fn restore_source(staging: &Path, source: &Path, wrote_manifest: bool) {
+    if wrote_manifest {
+        let _ = std::fs::remove_file(staging.join(MANIFEST_FILE));
+    }
+    if let Err(e) = std::fs::rename(staging, source) {
+        tracing::error!(
+            "couldn't move {} back to {}, the files are still there: {e}",
+            staging.display(),
+            source.display()
+        );
+    }
+}
+
+/// Write a manifest titled after the source folder unless the game has one
+///
+/// Returns whether a manifest was written.
+fn ensure_manifest(dir: &Path, source: &Path) -> Result<bool, SdrError> {
+    let path = dir.join(MANIFEST_FILE);
+    if path.exists() {
+        return Ok(false);
+    }
+    let manifest = Manifest {
+        title: source
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        ..Manifest::default()
+    };
+    persist::save_json(&path, &manifest)?;
+    Ok(true)
+}
+
+/// Recursively copy `from` to `to`
+///
+/// Symlinks are skipped, so nothing outside the source folder is pulled in.
+fn copy_dir(from: &Path, to: &Path) -> Result<(), SdrError> {
+    std::fs::create_dir_all(to)
+        .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", to.display())))?;
+    let entries = std::fs::read_dir(from)
+        .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", from.display())))?;
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", from.display())))?;
+        let file_type = entry
+            .file_type()
+            .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", entry.path().display())))?;
+        let dest = to.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else if file_type.is_file() {
+            std::fs::copy(entry.path(), &dest).map_err(|e| {
+                SdrError::Io(format!("Failed to copy {}: {e}", entry.path().display()))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A game folder named `name` outside any games directory
+    fn source_folder(parent: &Path, name: &str) -> std::path::PathBuf {
+        let source = parent.join(name);
+        std::fs::create_dir_all(source.join("client")).unwrap();
+        std::fs::write(source.join("client/game.js"), b"run()").unwrap();
+        source
+    }
+
+    #[test]
+    fn a_copied_import_is_listed_as_installed() {
+        let (games_dir, outside) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let source = source_folder(outside.path(), "My Game");
+
+        let imported = import_game(games_dir.path(), &source, "2024-01-01", false, false).unwrap();
+
+        assert_eq!(imported.title, "My Game");
+        let installed = install::list_installed_games(games_dir.path()).unwrap();
+        assert_eq!(installed, [imported]);
+        assert!(source.join("client/game.js").is_file());
+        assert!(!source.join(MANIFEST_FILE).exists());
+    }
+
+    #[test]
+    fn a_moved_import_takes_the_folder() {
+        let (games_dir, outside) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let source = source_folder(outside.path(), "My Game");
+
+        import_game(games_dir.path(), &source, "2024-01-01", true, false).unwrap();
+
+        assert!(!source.exists());
+        let installed = install::list_installed_games(games_dir.path()).unwrap();
+        assert_eq!(installed.len(), 1);
+        assert!(installed[0].install_path.join("client/game.js").is_file());
+    }
+
+    #[test]
+    fn a_failed_move_puts_the_folder_back() {
+        let (games_dir, outside) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let source = source_folder(outside.path(), "My Game");
+        // A file where the install goes can't be replaced like a folder
+        std::fs::write(paths::game_dir(games_dir.path(), "2024-01-01"), b"").unwrap();
+
+        let error = import_game(games_dir.path(), &source, "2024-01-01", true, true).unwrap_err();
+
+        assert_eq!(error.kind(), "Io", "{error}");
+        assert!(source.join("client/game.js").is_file());
+        assert!(!source.join(MANIFEST_FILE).exists());
+        assert!(!paths::import_staging_dir(games_dir.path(), "2024-01-01").exists());
+    }
+
+    #[test]
+    fn leftovers_of_an_interrupted_import_are_kept() {
+        let (games_dir, outside) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let source = source_folder(outside.path(), "My Game");
+        let leftover = paths::import_staging_dir(games_dir.path(), "2024-01-01");
+        std::fs::create_dir_all(&leftover).unwrap();
+        std::fs::write(leftover.join("save.dat"), b"progress").unwrap();
+
+        let error = import_game(games_dir.path(), &source, "2024-01-01", true, false).unwrap_err();
+
+        assert_eq!(error.kind(), "Invalid", "{error}");
+        assert!(leftover.join("save.dat").is_file());
+        assert!(source.join("client/game.js").is_file());
+    }
+
+    #[test]
+    fn an_overwriting_import_replaces_the_old_install() {
+        let (games_dir, outside) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let target = paths::game_dir(games_dir.path(), "2024-01-01");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("old.js"), b"old").unwrap();
+        let source = source_folder(outside.path(), "My Game");
+
+        import_game(games_dir.path(), &source, "2024-01-01", false, true).unwrap();
+
+        assert!(target.join("client/game.js").is_file());
+        assert!(!target.join("old.js").exists());
+        assert!(!paths::replaced_install_dir(games_dir.path(), "2024-01-01").exists());
+    }
+
+    #[test]
+    fn the_old_install_is_restored_when_the_new_one_cant_be_moved_in() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let target = paths::game_dir(games_dir.path(), "2024-01-01");
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("save.dat"), b"progress").unwrap();
+        let previous = paths::replaced_install_dir(games_dir.path(), "2024-01-01");
+        // Nothing is staged, so moving it in fails after the old install was moved aside
+        let staging = paths::import_staging_dir(games_dir.path(), "2024-01-01");
+
+        let error = replace_install(&staging, &target, &previous).unwrap_err();
+
+        assert_eq!(error.kind(), "Io", "{error}");
+        assert_eq!(std::fs::read(target.join("save.dat")).unwrap(), b"progress");
+        assert!(!previous.exists());
+    }
+}
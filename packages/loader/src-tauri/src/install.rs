@@ -0,0 +1,461 @@
+// Managing games installed in the games directory
+
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::checksum;
+use crate::error::SdrError;
+use crate::models::{Game, InstalledGame, Manifest};
+use crate::paths;
+use crate::persist;
+
+/// Name of the per-game manifest inside an install folder
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Files that mark a folder as a game even without a manifest
+const GAME_MARKERS: &[&str] = &["metadata.json", "client/game.js"];
+
+/// Every game installed in `games_dir`, sorted by id
+///
+/// Folders without a manifest or any recognizable game files are skipped, and
+/// an entry that can't be read is left out rather than failing the whole scan.
+pub fn list_installed_games(games_dir: &Path) -> Result<Vec<InstalledGame>, SdrError> {
+    let entries = match std::fs::read_dir(games_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(SdrError::Io(format!(
+                "Failed to read {}: {e}",
+                games_dir.display()
+            )))
+        }
+    };
+
+    let mut games: Vec<InstalledGame> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter_map(|entry| {
+            let id = entry.file_name().into_string().ok()?;
+            paths::check_game_date(&id).ok()?;
+            read_installed_game(&entry.path(), id)
+        })
+        .collect();
+    games.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(games)
+}
+
+/// Describe the install folder at `dir`, or `None` if it doesn't look like a game
+///
+/// A game whose manifest is missing or invalid is still listed, just not launchable.
+pub fn read_installed_game(dir: &Path, id: String) -> Option<InstalledGame> {
+    let has_manifest = dir.join(MANIFEST_FILE).is_file();
+    if !has_manifest && !GAME_MARKERS.iter().any(|marker| dir.join(marker).is_file()) {
+        return None;
+    }
+
+    let manifest = read_manifest(dir).ok();
+    let launchable = manifest
+        .as_ref()
+        .is_some_and(|manifest| check_manifest(dir, manifest).is_ok());
+    let title = manifest
+        .as_ref()
+        .map(|m| m.title.clone())
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| id.clone());
+
+    Some(InstalledGame {
+        size_on_disk: dir_size(dir).ok()?,
+        install_path: dir.to_path_buf(),
+        title,
+        id,
+        manifest,
+        launchable,
+    })
+}
+
+/// Parse the `manifest.json` in an install folder
+pub fn read_manifest(dir: &Path) -> Result<Manifest, SdrError> {
+    let path = dir.join(MANIFEST_FILE);
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", path.display())))?;
+    serde_json::from_str(&text)
+        .map_err(|e| SdrError::Parse(format!("Invalid manifest {}: {e}", path.display())))
+}
+
+/// Note which server build a freshly installed game came from in its manifest
+///
+/// Also stores the hash of every file, for `verify_installed` to check later.
+/// Other manifest fields are kept as they are; a game without a manifest gets one
+/// holding just the build info. Reads every file, so run it off the async runtime.
+pub fn record_install(dir: &Path, game: &Game) -> Result<(), SdrError> {
+    let files = hash_files(dir)?;
+    let path = dir.join(MANIFEST_FILE);
+    let mut manifest = match std::fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&text)
+            .map_err(|e| SdrError::Parse(format!("Invalid manifest {}: {e}", path.display())))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => serde_json::Map::new(),
+        Err(e) => {
+            return Err(SdrError::Io(format!(
+                "Failed to read {}: {e}",
+                path.display()
+            )))
+        }
+    };
+    manifest.insert(String::from("checksum"), game.checksum.clone().into());
+    manifest.insert(String::from("version"), game.version.clone().into());
+    manifest.insert(
+        String::from("files"),
+        serde_json::to_value(files)
+            .map_err(|e| SdrError::Parse(format!("Failed to serialize file hashes: {e}")))?,
+    );
+
+    persist::save_json(&path, &manifest)
+}
+
+/// SHA-256 of every file under `dir` except the manifest, keyed by `/`-separated relative path
+///
+/// Symlinks are not followed.
+pub fn hash_files(dir: &Path) -> Result<BTreeMap<String, String>, SdrError> {
+    let mut files = BTreeMap::new();
+    hash_files_into(dir, dir, &mut files)?;
+    files.remove(MANIFEST_FILE);
+    Ok(files)
+}
+
+fn hash_files_into(
+    root: &Path,
+    dir: &Path,
+    files: &mut BTreeMap<String, String>,
+) -> Result<(), SdrError> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", dir.display())))?;
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", dir.display())))?;
+        let path = entry.path();
+        let file_type = entry
+            .file_type()
+            .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", path.display())))?;
+        if file_type.is_dir() {
+            hash_files_into(root, &path, files)?;
+        } else if file_type.is_file() {
+            files.insert(
+                relative_key(root, &path),
+                checksum::hash_file_blocking(&path)?,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `path` relative to `root`, with `/` separators on every platform
+pub fn relative_key(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Check that the manifest's executable and working dir stay inside `dir`
+pub fn check_manifest(dir: &Path, manifest: &Manifest) -> Result<(), SdrError> {
+    if manifest.executable.trim().is_empty() {
+        return Err(SdrError::Invalid(String::from(
+            "The manifest doesn't name an executable",
+        )));
+    }
+    let executable = resolve_inside(dir, &manifest.executable)?;
+    if executable == normalize(dir) {
+        return Err(SdrError::Invalid(format!(
+            "{:?} is not an executable",
+            manifest.executable
+        )));
+    }
+    if let Some(working_dir) = &manifest.working_dir {
+        resolve_inside(dir, working_dir)?;
+    }
+    Ok(())
+}
+
+/// Resolve a manifest path against `dir`, rejecting anything that points outside it
+pub fn resolve_inside(dir: &Path, path: &str) -> Result<PathBuf, SdrError> {
+    let root = normalize(dir);
+    let candidate = Path::new(path);
+    let resolved = if candidate.is_absolute() {
+        normalize(candidate)
+    } else {
+        normalize(&root.join(candidate))
+    };
+    if resolved.starts_with(&root) {
+        Ok(resolved)
+    } else {
+        Err(SdrError::Invalid(format!(
+            "{path:?} points outside the game folder"
+        )))
+    }
+}
+
+/// Lexically resolve `.` and `..` without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Total size of the files under `path`
+///
+/// Symlinks are not followed, so nothing outside the tree is counted.
+pub fn dir_size(path: &Path) -> Result<u64, SdrError> {
+    let metadata = std::fs::symlink_metadata(path)
+        .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", path.display())))?;
+    if metadata.is_file() {
+        return Ok(metadata.len());
+    }
+    if !metadata.is_dir() {
+        return Ok(0);
+    }
+
+    let mut total = 0;
+    let entries = std::fs::read_dir(path)
+        .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", path.display())))?;
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", path.display())))?;
+        total += dir_size(&entry.path())?;
+    }
+    Ok(total)
+}
+
+/// Bytes used by everything in `games_dir`, including partial downloads
+///
+/// Built on `dir_size`, so symlinks add nothing and are never followed.
+pub fn installed_size(games_dir: &Path) -> Result<u64, SdrError> {
+    if !games_dir.exists() {
+        return Ok(0);
+    }
+    dir_size(games_dir)
+}
+
+/// Delete an installed game and return how many bytes were freed
+///
+/// Uninstalling a game that isn't installed returns `Ok(0)`. The path is
+/// canonicalized first so nothing outside `games_dir` can be removed.
+pub fn uninstall_game(games_dir: &Path, game_date: &str) -> Result<u64, SdrError> {
+    paths::check_game_date(game_date)?;
+    let dir = paths::game_dir(games_dir, game_date);
+    let target = match dir.canonicalize() {
+        Ok(target) => target,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => {
+            return Err(SdrError::Io(format!(
+                "Failed to resolve {}: {e}",
+                dir.display()
+            )))
+        }
+    };
+    let root = games_dir
+        .canonicalize()
+        .map_err(|e| SdrError::Io(format!("Failed to resolve {}: {e}", games_dir.display())))?;
+    if target == root || !target.starts_with(&root) {
+        return Err(SdrError::Invalid(format!(
+            "Refusing to delete {}: it is outside the games directory",
+            target.display()
+        )));
+    }
+    if !target.is_dir() {
+        return Err(SdrError::Invalid(format!(
+            "{} is not a game directory",
+            target.display()
+        )));
+    }
+
+    let freed = dir_size(&target)?;
+    std::fs::remove_dir_all(&target)
+        .map_err(|e| SdrError::Io(format!("Failed to delete {}: {e}", target.display())))?;
+    Ok(freed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Install folder for `game_date` holding `files`
+    fn fake_install(games_dir: &Path, game_date: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir = paths::game_dir(games_dir, game_date);
+        for (path, contents) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn uninstall_reports_the_bytes_freed() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let dir = fake_install(
+            games_dir.path(),
+            "2024-01-01",
+            &[("index.html", &[0; 300]), ("client/game.js", &[0; 700])],
+        );
+
+        let freed = uninstall_game(games_dir.path(), "2024-01-01").unwrap();
+
+        assert_eq!(freed, 1000);
+        assert!(!dir.exists());
+        assert!(games_dir.path().is_dir());
+    }
+
+    #[test]
+    fn uninstalling_twice_frees_nothing_the_second_time() {
+        let games_dir = tempfile::tempdir().unwrap();
+        fake_install(games_dir.path(), "2024-01-01", &[("index.html", b"<html>")]);
+
+        uninstall_game(games_dir.path(), "2024-01-01").unwrap();
+        assert_eq!(uninstall_game(games_dir.path(), "2024-01-01").unwrap(), 0);
+    }
+
+    #[test]
+    fn uninstall_refuses_ids_escaping_the_games_dir() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let error = uninstall_game(games_dir.path(), "..").unwrap_err();
+        assert_eq!(error.kind(), "Invalid");
+        assert!(games_dir.path().is_dir());
+    }
+
+    #[test]
+    fn lists_folders_that_look_like_games() {
+        let games_dir = tempfile::tempdir().unwrap();
+        fake_install(
+            games_dir.path(),
+            "2024-01-02",
+            &[
+                (
+                    MANIFEST_FILE,
+                    br#"{"title": "Second", "executable": "run.sh"}"#,
+                ),
+                ("run.sh", b"#!/bin/sh"),
+            ],
+        );
+        fake_install(games_dir.path(), "2024-01-01", &[("metadata.json", b"{}")]);
+        fake_install(
+            games_dir.path(),
+            "notes",
+            &[("todo.txt", b"nothing to see")],
+        );
+        fake_install(
+            games_dir.path(),
+            ".2024-01-03.extracting",
+            &[(MANIFEST_FILE, b"{}")],
+        );
+        std::fs::write(paths::part_path(games_dir.path(), "2024-01-04"), b"").unwrap();
+
+        let games = list_installed_games(games_dir.path()).unwrap();
+
+        let ids: Vec<&str> = games.iter().map(|game| game.id.as_str()).collect();
+        assert_eq!(ids, ["2024-01-01", "2024-01-02"]);
+        // Without a manifest the folder name stands in for the title
+        assert_eq!(games[0].title, "2024-01-01");
+        assert!(!games[0].launchable);
+        assert_eq!(games[1].title, "Second");
+        assert!(games[1].launchable);
+        assert_eq!(games[1].install_path, games_dir.path().join("2024-01-02"));
+    }
+
+    #[test]
+    fn a_missing_games_dir_has_no_games() {
+        let parent = tempfile::tempdir().unwrap();
+        let games = list_installed_games(&parent.path().join("games")).unwrap();
+        assert!(games.is_empty());
+    }
+
+    #[test]
+    fn record_install_round_trips_through_the_manifest() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let dir = fake_install(
+            games_dir.path(),
+            "2024-01-01",
+            &[
+                (
+                    MANIFEST_FILE,
+                    br#"{"title": "Tetris", "executable": "bin/run", "args": ["--fullscreen"], "extra": 1}"#,
+                ),
+                ("bin/run", b"#!/bin/sh"),
+            ],
+        );
+        let game = Game {
+            checksum: Some(String::from("abc123")),
+            version: Some(String::from("2")),
+            ..crate::test_support::game("tetris", "2024-01-01")
+        };
+
+        record_install(&dir, &game).unwrap();
+        let manifest = read_manifest(&dir).unwrap();
+
+        assert_eq!(manifest.title, "Tetris");
+        assert_eq!(manifest.executable, "bin/run");
+        assert_eq!(manifest.args, ["--fullscreen"]);
+        assert_eq!(manifest.checksum.as_deref(), Some("abc123"));
+        assert_eq!(manifest.version.as_deref(), Some("2"));
+        assert_eq!(
+            manifest.files.keys().collect::<Vec<_>>(),
+            [&String::from("bin/run")]
+        );
+        check_manifest(&dir, &manifest).unwrap();
+        // Fields the loader doesn't know are kept
+        let raw = std::fs::read_to_string(dir.join(MANIFEST_FILE)).unwrap();
+        assert!(raw.contains("\"extra\""), "{raw}");
+    }
+
+    #[test]
+    fn an_executable_outside_the_game_folder_is_flagged() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let dir = fake_install(
+            games_dir.path(),
+            "2024-01-01",
+            &[(MANIFEST_FILE, br#"{"executable": "../../bin/sh"}"#)],
+        );
+
+        let error = check_manifest(&dir, &read_manifest(&dir).unwrap()).unwrap_err();
+        assert_eq!(error.kind(), "Invalid", "{error}");
+
+        let game = list_installed_games(games_dir.path()).unwrap().remove(0);
+        assert!(game.manifest.is_some());
+        assert!(!game.launchable);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn installed_size_adds_up_games_and_partial_downloads_but_not_symlinks() {
+        let (games_dir, outside) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        fake_install(games_dir.path(), "2024-01-01", &[("index.html", &[0; 300])]);
+        let dir = fake_install(
+            games_dir.path(),
+            "2024-01-02",
+            &[("client/game.js", &[0; 700])],
+        );
+        std::fs::write(paths::part_path(games_dir.path(), "2024-01-03"), [0; 24]).unwrap();
+        let big = outside.path().join("big.bin");
+        std::fs::write(&big, vec![0; 10_000]).unwrap();
+        std::os::unix::fs::symlink(&big, dir.join("linked.bin")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.join("linked-dir")).unwrap();
+
+        assert_eq!(installed_size(games_dir.path()).unwrap(), 1024);
+        assert_eq!(dir_size(&dir).unwrap(), 700);
+    }
+
+    #[test]
+    fn a_missing_games_dir_uses_no_space() {
+        let parent = tempfile::tempdir().unwrap();
+        assert_eq!(installed_size(&parent.path().join("games")).unwrap(), 0);
+    }
+}
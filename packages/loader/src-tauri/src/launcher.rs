@@ -0,0 +1,196 @@
+// Starting installed games
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::SdrError;
+use crate::install;
+use crate::paths;
+
+/// Games started from the loader that haven't exited yet, held in Tauri managed state
+#[derive(Default)]
+pub struct RunningGames {
+    /// Running processes per game date
+    games: Mutex<HashMap<String, usize>>,
+}
+
+impl RunningGames {
+    /// Note one more process of `game_date`
+    pub fn start(&self, game_date: &str) {
+        *self
+            .games
+            .lock()
+            .unwrap()
+            .entry(game_date.to_string())
+            .or_default() += 1;
+    }
+
+    /// Note that a process of `game_date` exited
+    pub fn finish(&self, game_date: &str) {
+        let mut games = self.games.lock().unwrap();
+        if let Some(count) = games.get_mut(game_date) {
+            *count -= 1;
+            if *count == 0 {
+                games.remove(game_date);
+            }
+        }
+    }
+
+    pub fn is_running(&self, game_date: &str) -> bool {
+        self.games.lock().unwrap().contains_key(game_date)
+    }
+}
+
+/// Start an installed game as a detached process and return its PID
+///
+/// The manifest must name an executable inside the game folder. The child is
+/// reaped on a background thread so it never lingers as a zombie, which then
+/// calls `on_exit` with how long the game ran.
+pub fn launch_game(
+    games_dir: &Path,
+    game_date: &str,
+    on_exit: impl FnOnce(Duration) + Send + 'static,
+) -> Result<u32, SdrError> {
+    paths::check_game_date(game_date)?;
+    let dir = paths::game_dir(games_dir, game_date);
+    if !dir.is_dir() {
+        return Err(SdrError::NotFound(format!("{game_date} is not installed")));
+    }
+
+    let not_launchable =
+        |e: SdrError| SdrError::Invalid(format!("{game_date} can't be launched: {e}"));
+    let manifest = install::read_manifest(&dir).map_err(not_launchable)?;
+    install::check_manifest(&dir, &manifest).map_err(not_launchable)?;
+
+    let executable = install::resolve_inside(&dir, &manifest.executable)?;
+    if !executable.is_file() {
+        return Err(SdrError::NotFound(format!(
+            "Executable {} is missing",
+            executable.display()
+        )));
+    }
+    // Catch symlinks inside the folder that point somewhere else
+    let real_dir = dir
+        .canonicalize()
+        .map_err(|e| SdrError::Io(format!("Failed to resolve {}: {e}", dir.display())))?;
+    let real_executable = executable
+        .canonicalize()
+        .map_err(|e| SdrError::Io(format!("Failed to resolve {}: {e}", executable.display())))?;
+    if !real_executable.starts_with(&real_dir) {
+        return Err(not_launchable(SdrError::Invalid(format!(
+            "{} points outside the game folder",
+            executable.display()
+        ))));
+    }
+
+    let working_dir = match &manifest.working_dir {
+        Some(working_dir) => install::resolve_inside(&dir, working_dir)?,
+        None => dir.clone(),
+    };
+
+    #[cfg(unix)]
+    ensure_executable(&real_executable)?;
+
+    let mut command = Command::new(&real_executable);
+    command
+        .args(&manifest.args)
+        .current_dir(&working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Own process group, so signals aimed at the loader don't reach the game
+        command.process_group(0);
+    }
+
+    let mut child = command.spawn().map_err(|e| {
+        SdrError::Io(format!(
+            "Failed to start {}: {e}",
+            real_executable.display()
+        ))
+    })?;
+    let pid = child.id();
+    let started = Instant::now();
+    std::thread::spawn(move || {
+        let _ = child.wait();
+        on_exit(started.elapsed());
+    });
+    Ok(pid)
+}
+
+/// Set the executable bits if the archive didn't preserve them
+#[cfg(unix)]
+fn ensure_executable(path: &Path) -> Result<(), SdrError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = std::fs::metadata(path)
+        .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", path.display())))?;
+    let mut permissions = metadata.permissions();
+    if permissions.mode() & 0o111 == 0 {
+        permissions.set_mode(permissions.mode() | 0o755);
+        std::fs::set_permissions(path, permissions).map_err(|e| {
+            SdrError::Io(format!("Failed to make {} executable: {e}", path.display()))
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn launches_the_manifest_executable_and_reports_the_exit() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let dir = paths::game_dir(games_dir.path(), "2024-01-01");
+        std::fs::create_dir_all(dir.join("bin")).unwrap();
+        std::fs::write(
+            dir.join(install::MANIFEST_FILE),
+            br#"{"executable": "bin/run.sh", "args": ["hello"]}"#,
+        )
+        .unwrap();
+        // Not executable yet, the launcher sets the bits
+        std::fs::write(
+            dir.join("bin/run.sh"),
+            "#!/bin/sh\necho \"$1\" > launched.txt\n",
+        )
+        .unwrap();
+        let (on_exit, exited) = std::sync::mpsc::channel();
+
+        let pid = launch_game(games_dir.path(), "2024-01-01", move |ran| {
+            on_exit.send(ran).unwrap();
+        })
+        .unwrap();
+
+        assert!(pid > 0);
+        exited.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("launched.txt")).unwrap(),
+            "hello\n"
+        );
+    }
+
+    #[test]
+    fn launching_a_missing_game_fails() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let error = launch_game(games_dir.path(), "2024-01-01", |_| {}).unwrap_err();
+        assert_eq!(error.kind(), "NotFound");
+    }
+
+    #[test]
+    fn running_games_are_counted_per_process() {
+        let running = RunningGames::default();
+        running.start("2024-01-01");
+        running.start("2024-01-01");
+        running.finish("2024-01-01");
+        assert!(running.is_running("2024-01-01"));
+        running.finish("2024-01-01");
+        assert!(!running.is_running("2024-01-01"));
+    }
+}
@@ -1 +1,178 @@
+pub mod api;
+pub mod bandwidth;
+pub mod catalog;
+pub mod checksum;
+pub mod cleanup;
 pub mod commands;
+pub mod config;
+pub mod disk;
+pub mod download;
+pub mod error;
+pub mod extract;
+pub mod favorites;
+pub mod health;
+pub mod import;
+pub mod install;
+pub mod launcher;
+pub mod live;
+pub mod logging;
+pub mod models;
+pub mod paths;
+pub mod persist;
+pub mod profile;
+pub mod prune;
+pub mod queue;
+pub mod randomizer;
+pub mod ratelimit;
+pub mod search;
+pub mod stats;
+pub mod sync;
+pub mod thumbnails;
+pub mod tls;
+pub mod updates;
+pub mod verify;
+
+#[cfg(test)]
+mod test_support;
+
+use std::time::Duration;
+
+use api::{ApiClient, HttpSettings, RetryPolicy};
+use config::{ConfigStore, ServerConfig};
+use download::ActiveDownloads;
+use launcher::RunningGames;
+use live::LiveSettings;
+use queue::DownloadQueue;
+use stats::StatsStore;
+use sync::AutoSync;
+
+/// Build and run the Tauri app
+pub fn run() {
+    let _log_guard = logging::init();
+    let config = ConfigStore::open().expect("failed to locate the loader config directory");
+    let settings = config.get();
+    let mut retry = RetryPolicy::default();
+    if let Some(attempts) = settings.fetch_attempts {
+        retry.max_attempts = attempts;
+    }
+    let mut http = HttpSettings::default();
+    if let Some(secs) = settings.request_timeout_secs {
+        http.request_timeout = Duration::from_secs(secs.max(1));
+    }
+    if let Some(secs) = settings.connect_timeout_secs {
+        http.connect_timeout = Duration::from_secs(secs.max(1));
+    }
+    http.cert_pin = settings.cert_pin.clone();
+    http.proxy_url = settings
+        .proxy_url
+        .clone()
+        .filter(|url| !url.trim().is_empty());
+    if let Some(Err(e)) = http.proxy_url.as_deref().map(api::parse_proxy) {
+        // The client refuses every request rather than connecting around the proxy
+        tracing::error!("{e}, the game server won't be contacted until proxy_url is fixed");
+    }
+    let api = ApiClient::new(ServerConfig::resolve(&settings))
+        .with_http(&http)
+        .with_retry(retry)
+        .with_api_key(settings.api_key.clone())
+        .with_bandwidth_limit(settings.max_download_bytes_per_sec)
+        .with_rate_limit(
+            settings
+                .max_requests_per_minute
+                .unwrap_or(ratelimit::DEFAULT_REQUESTS_PER_MINUTE),
+        );
+    let sync_interval = Duration::from_secs(
+        60 * settings
+            .sync_interval_mins
+            .unwrap_or(sync::DEFAULT_SYNC_INTERVAL_MINS)
+            .max(1),
+    );
+
+    let live = settings
+        .ws_url
+        .clone()
+        .filter(|url| !url.trim().is_empty())
+        .map(|url| LiveSettings {
+            url,
+            api_key: settings.api_key.clone(),
+            cert_pin: settings.cert_pin.clone(),
+        })
+        .filter(|live| {
+            if http.proxy_url.is_some() {
+                // The WebSocket client can't use a proxy, so it would go around it
+                tracing::warn!("live updates disabled: they can't go through proxy_url");
+                return false;
+            }
+            match live.validate() {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!("live updates disabled: {e}");
+                    false
+                }
+            }
+        });
+
+    tauri::Builder::default()
+        .manage(config)
+        .manage(StatsStore::open().expect("failed to locate the loader config directory"))
+        .manage(api)
+        .manage(DownloadQueue::new(
+            settings
+                .max_parallel_downloads
+                .unwrap_or(queue::DEFAULT_MAX_PARALLEL),
+        ))
+        .manage(ActiveDownloads::default())
+        .manage(RunningGames::default())
+        .manage(AutoSync::new(settings.auto_sync.unwrap_or(true)))
+        .setup(move |app| {
+            sync::spawn(app.handle().clone(), sync_interval);
+            cleanup::spawn_startup_cleanup(app.handle().clone());
+            if let Some(live) = live {
+                live::spawn(app.handle().clone(), live);
+            }
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            commands::fetch_games,
+            commands::search_games,
+            commands::filter_games,
+            commands::get_thumbnail,
+            commands::download_game,
+            commands::verify_game,
+            commands::verify_installed,
+            commands::enqueue_download,
+            commands::download_games,
+            commands::queue_status,
+            commands::pause_queue,
+            commands::resume_queue,
+            commands::cancel_download,
+            commands::get_games_dir,
+            commands::set_games_dir,
+            commands::get_config,
+            commands::health_check,
+            commands::set_auto_sync,
+            commands::free_space,
+            commands::installed_size,
+            commands::catalog_size,
+            commands::uninstall_game,
+            commands::prune_to_free,
+            commands::import_game,
+            commands::cleanup_partial_downloads,
+            commands::list_installed_games,
+            commands::pick_random_game,
+            commands::daily_game,
+            commands::daily_game_for,
+            commands::clear_history,
+            commands::add_favorite,
+            commands::remove_favorite,
+            commands::list_favorites,
+            commands::export_profile,
+            commands::import_profile,
+            commands::launch_game,
+            commands::record_play,
+            commands::get_stats,
+            commands::check_updates,
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
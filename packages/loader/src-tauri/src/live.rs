@@ -0,0 +1,379 @@
+// Games list updates pushed by the server over WebSocket
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+use tauri::{AppHandle, Emitter, Runtime};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::Connector;
+
+use crate::catalog::{self, GamesList};
+use crate::error::SdrError;
+use crate::models::Game;
+use crate::tls;
+
+/// Wait before the first reconnect, doubled after every failed one
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest wait between two connection attempts
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Change to the games list announced by the server
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum LiveMessage {
+    GameAdded { game: Game },
+    GameRemoved { id: String },
+}
+
+/// Where live updates come from and how to connect
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LiveSettings {
+    /// `ws://` or `wss://` endpoint
+    pub url: String,
+    /// Sent as a bearer token, only ever over `wss://`
+    pub api_key: Option<String>,
+    /// Pin of the game server certificate, checked like for HTTP requests
+    pub cert_pin: Option<String>,
+}
+
+impl LiveSettings {
+    fn api_key(&self) -> Option<&str> {
+        self.api_key
+            .as_deref()
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+    }
+
+    /// Check that `url` is a `ws://` or `wss://` URL the API key may be sent to
+    ///
+    /// An API key is refused over unencrypted `ws://`, where anyone on the
+    /// network could read it.
+    pub fn validate(&self) -> Result<(), SdrError> {
+        let url = &self.url;
+        let parsed = reqwest::Url::parse(url.trim())
+            .map_err(|e| SdrError::Invalid(format!("Invalid WebSocket URL {url:?}: {e}")))?;
+        match parsed.scheme() {
+            "wss" => Ok(()),
+            "ws" if self.api_key().is_none() => Ok(()),
+            "ws" => Err(SdrError::Invalid(format!(
+                "Not sending the API key over unencrypted {url}, use wss://"
+            ))),
+            _ => Err(SdrError::Invalid(format!(
+                "Invalid WebSocket URL {url:?}: expected ws:// or wss://"
+            ))),
+        }
+    }
+}
+
+/// Spawn the task that keeps a connection open and applies its updates
+///
+/// Each update is written to the games cache and emitted as `games-updated`.
+/// Dropped connections are retried with backoff, up to `MAX_RECONNECT_DELAY`;
+/// the periodic sync keeps running the whole time, so an unreachable endpoint
+/// only means updates arrive at the next poll.
+pub fn spawn<R: Runtime>(app: AppHandle<R>, settings: LiveSettings) {
+    tauri::async_runtime::spawn(async move {
+        let url = settings.url.clone();
+        let mut delay = INITIAL_RECONNECT_DELAY;
+        loop {
+            let connected = match catalog::cache_path() {
+                Ok(cache_path) => {
+                    listen(&settings, &cache_path, |list| {
+                        let _ = app.emit("games-updated", list);
+                    })
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            match connected {
+                Ok(()) => {
+                    delay = INITIAL_RECONNECT_DELAY;
+                    tracing::debug!(%url, "live updates disconnected");
+                }
+                Err(e) => tracing::debug!(%url, "live updates unavailable: {e}"),
+            }
+            tokio::time::sleep(delay).await;
+            delay = delay.saturating_mul(2).min(MAX_RECONNECT_DELAY);
+        }
+    });
+}
+
+/// Apply updates to the cache at `cache_path` until the connection drops
+///
+/// Every list that changed is passed to `on_update`. Fails if it can't connect.
+pub async fn listen(
+    settings: &LiveSettings,
+    cache_path: &Path,
+    on_update: impl Fn(GamesList),
+) -> Result<(), SdrError> {
+    settings.validate()?;
+    let url = settings.url.as_str();
+    let mut request = url
+        .into_client_request()
+        .map_err(|e| SdrError::Invalid(format!("Invalid WebSocket URL {url:?}: {e}")))?;
+    if let Some(api_key) = settings.api_key() {
+        let value = HeaderValue::from_str(&format!("Bearer {api_key}"))
+            .map_err(|e| SdrError::Invalid(format!("Invalid API key: {e}")))?;
+        request.headers_mut().insert("Authorization", value);
+    }
+    let connector = tls::normalize_pin(settings.cert_pin.as_deref())
+        .map(|pin| Connector::Rustls(Arc::new(tls::pinned_config(&pin))));
+    let (mut stream, _) =
+        tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector)
+            .await
+            .map_err(|e| match tls::find_pin_mismatch(&e) {
+                Some(mismatch) => {
+                    SdrError::CertPinMismatch(format!("Could not connect to {url}: {mismatch}"))
+                }
+                None => SdrError::Network(format!("Could not connect to {url}: {e}")),
+            })?;
+    tracing::info!(%url, "connected for live updates");
+
+    while let Some(frame) = stream.next().await {
+        let text = match frame {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => break,
+            Ok(_) => continue,
+            Err(e) => {
+                tracing::warn!(%url, "live updates connection dropped: {e}");
+                break;
+            }
+        };
+        match serde_json::from_str::<LiveMessage>(&text) {
+            Ok(message) => match apply_to_cache(cache_path, message) {
+                Ok(Some(list)) => on_update(list),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("failed to apply a live update: {e}"),
+            },
+            Err(e) => tracing::debug!("ignoring live message {text:?}: {e}"),
+        }
+    }
+    Ok(())
+}
+
+/// Apply `message` to the cached list at `cache_path`, returning the list if it changed
+///
+/// Without a cached list there is nothing to update; the next fetch gets the
+/// full list, change included.
+fn apply_to_cache(cache_path: &Path, message: LiveMessage) -> Result<Option<GamesList>, SdrError> {
+    let Some(mut cache) = catalog::load_cache(cache_path) else {
+        return Ok(None);
+    };
+    if !apply(&mut cache.games, message) {
+        return Ok(None);
+    }
+    catalog::save_cache(cache_path, &cache)?;
+    Ok(Some(GamesList {
+        games: cache.games,
+        last_fetched: cache.last_fetched,
+        from_cache: false,
+    }))
+}
+
+/// Apply `message` to `games`, returning `false` if it changed nothing
+///
+/// An added game replaces any game with the same id.
+pub fn apply(games: &mut Vec<Game>, message: LiveMessage) -> bool {
+    match message {
+        LiveMessage::GameAdded { game } => {
+            if game.id.trim().is_empty() || game.title.trim().is_empty() {
+                return false;
+            }
+            match games.iter_mut().find(|existing| existing.id == game.id) {
+                Some(existing) if *existing == game => false,
+                Some(existing) => {
+                    *existing = game;
+                    true
+                }
+                None => {
+                    games.push(game);
+                    true
+                }
+            }
+        }
+        LiveMessage::GameRemoved { id } => {
+            let before = games.len();
+            games.retain(|game| game.id != id);
+            games.len() != before
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use chrono::Utc;
+    use futures_util::SinkExt;
+    use sha2::{Digest, Sha256};
+    use tokio::io::{AsyncRead, AsyncWrite};
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+    use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+    use super::*;
+    use crate::catalog::CachedGames;
+    use crate::test_support::{self, game};
+
+    /// `Authorization` header of every connection a `ws_server` accepted
+    type Log = Arc<Mutex<Vec<Option<String>>>>;
+
+    /// WebSocket server sending `messages` on every connection, then closing it
+    ///
+    /// Serves `wss://` when given a TLS acceptor.
+    async fn ws_server(tls: Option<TlsAcceptor>, messages: Vec<String>) -> (String, Log) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let scheme = if tls.is_some() { "wss" } else { "ws" };
+        let url = format!("{scheme}://{}", listener.local_addr().unwrap());
+        let log = Log::default();
+        let connections = log.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (messages, log) = (messages.clone(), connections.clone());
+                match &tls {
+                    Some(tls) => {
+                        if let Ok(stream) = tls.accept(stream).await {
+                            serve(stream, messages, log).await;
+                        }
+                    }
+                    None => serve(stream, messages, log).await,
+                }
+            }
+        });
+        (url, log)
+    }
+
+    async fn serve<S: AsyncRead + AsyncWrite + Unpin>(stream: S, messages: Vec<String>, log: Log) {
+        // The error type is fixed by tungstenite's handshake callback
+        #[allow(clippy::result_large_err)]
+        let record = |request: &Request, response: Response| {
+            let authorization = request
+                .headers()
+                .get("Authorization")
+                .and_then(|value| value.to_str().ok())
+                .map(String::from);
+            log.lock().unwrap().push(authorization);
+            Ok(response)
+        };
+        let Ok(mut socket) = tokio_tungstenite::accept_hdr_async(stream, record).await else {
+            return;
+        };
+        for message in messages {
+            let _ = socket.send(Message::text(message)).await;
+        }
+        let _ = socket.close(None).await;
+    }
+
+    fn game_added(id: &str, date: &str) -> String {
+        serde_json::json!({ "type": "game-added", "game": game(id, date) }).to_string()
+    }
+
+    /// Games cache in `dir` holding only `g1`
+    fn cache_in(dir: &Path) -> std::path::PathBuf {
+        let cache_path = dir.join("games.json");
+        let cache = CachedGames {
+            games: vec![game("g1", "2024-01-01")],
+            last_fetched: Utc::now(),
+            etag: None,
+        };
+        catalog::save_cache(&cache_path, &cache).unwrap();
+        cache_path
+    }
+
+    /// Ids of the games in every list passed to `on_update`, in order
+    async fn listen_for_updates(
+        settings: &LiveSettings,
+        cache_path: &Path,
+    ) -> Result<Vec<Vec<String>>, SdrError> {
+        let updates = Mutex::new(Vec::new());
+        listen(settings, cache_path, |list| {
+            let ids = list.games.into_iter().map(|game| game.id).collect();
+            updates.lock().unwrap().push(ids);
+        })
+        .await?;
+        Ok(updates.into_inner().unwrap())
+    }
+
+    fn cached_ids(cache_path: &Path) -> Vec<String> {
+        let cache = catalog::load_cache(cache_path).unwrap();
+        cache.games.into_iter().map(|game| game.id).collect()
+    }
+
+    #[tokio::test]
+    async fn a_pushed_game_shows_up_in_the_cached_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = cache_in(dir.path());
+        let messages = vec![
+            game_added("g2", "2024-01-02"),
+            String::from("not a live message"),
+            game_added("g2", "2024-01-02"),
+        ];
+        let (url, connections) = ws_server(None, messages).await;
+        let settings = LiveSettings {
+            url,
+            ..LiveSettings::default()
+        };
+
+        let updates = listen_for_updates(&settings, &cache_path).await.unwrap();
+
+        // The repeated message changed nothing, so it isn't reported
+        assert_eq!(updates, [["g1", "g2"]]);
+        assert_eq!(cached_ids(&cache_path), ["g1", "g2"]);
+        assert_eq!(*connections.lock().unwrap(), [None]);
+    }
+
+    #[tokio::test]
+    async fn the_api_key_is_never_sent_over_plain_ws() {
+        let dir = tempfile::tempdir().unwrap();
+        let (url, connections) = ws_server(None, Vec::new()).await;
+        let settings = LiveSettings {
+            url,
+            api_key: Some(String::from("secret")),
+            cert_pin: None,
+        };
+
+        let error = listen_for_updates(&settings, &dir.path().join("games.json"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(error.kind(), "Invalid", "{error}");
+        assert!(connections.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn wss_connections_are_checked_against_the_pin() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = cache_in(dir.path());
+        let (acceptor, certificate) = test_support::self_signed_tls();
+        let (url, connections) =
+            ws_server(Some(acceptor), vec![game_added("g2", "2024-01-02")]).await;
+        let pinned = LiveSettings {
+            url,
+            api_key: Some(String::from("secret")),
+            cert_pin: Some(format!("{:x}", Sha256::digest(&certificate))),
+        };
+        let mispinned = LiveSettings {
+            cert_pin: Some("00".repeat(32)),
+            ..pinned.clone()
+        };
+
+        let error = listen_for_updates(&mispinned, &cache_path)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), "CertPinMismatch", "{error}");
+        assert!(connections.lock().unwrap().is_empty());
+
+        let updates = listen_for_updates(&pinned, &cache_path).await.unwrap();
+        assert_eq!(updates, [["g1", "g2"]]);
+        assert_eq!(
+            *connections.lock().unwrap(),
+            [Some(String::from("Bearer secret"))]
+        );
+    }
+}
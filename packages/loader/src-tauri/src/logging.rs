@@ -0,0 +1,50 @@
+// Log output to stderr and a rolling file
+
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+use crate::paths;
+
+/// Env var holding the log filter, e.g. `debug` or `sdr_loader_lib=trace`
+pub const LOG_ENV: &str = "SDR_LOG";
+
+/// Level logged when `SDR_LOG` is unset or invalid
+const DEFAULT_FILTER: &str = "info";
+
+/// Prefix of the daily log files in the log dir
+const LOG_FILE: &str = "loader.log";
+
+/// Install the global subscriber, logging to stderr and a daily log file
+///
+/// The returned guard flushes the file on drop, so keep it alive until the app
+/// exits. If the log dir can't be created, only stderr is logged to.
+pub fn init() -> Option<WorkerGuard> {
+    let filter =
+        EnvFilter::try_from_env(LOG_ENV).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let stderr = fmt::layer().with_writer(std::io::stderr);
+
+    let log_dir = paths::log_dir();
+    let (file, guard) = match &log_dir {
+        Ok(dir) => {
+            let appender = tracing_appender::rolling::daily(dir, LOG_FILE);
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (
+                Some(fmt::layer().with_ansi(false).with_writer(writer)),
+                Some(guard),
+            )
+        }
+        Err(_) => (None, None),
+    };
+
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(stderr)
+        .with(file)
+        .try_init();
+    if let Err(e) = log_dir {
+        tracing::warn!("logging to stderr only: {e}");
+    }
+    guard
+}
@@ -2,6 +2,5 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
-    // TODO: Initialize Tauri app
-    println!("Steam Deck Randomizer - Tauri backend");
+    sdr_loader_lib::run();
 }
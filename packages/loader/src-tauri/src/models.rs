@@ -0,0 +1,132 @@
+// Data types shared between the loader commands and the frontend
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// A game as advertised by the game server
+///
+/// Missing fields fall back to their defaults and unknown fields are ignored,
+/// so a newer server schema doesn't break older loader builds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Game {
+    pub id: String,
+    pub date: String,
+    pub title: String,
+    pub size_bytes: u64,
+    pub checksum: Option<String>,
+    /// Build version, bumped by the server when the game is repackaged
+    pub version: Option<String>,
+    /// Cover art, either absolute or relative to the game server
+    pub thumbnail_url: Option<String>,
+}
+
+/// Response body of the server's games list endpoint
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GamesResponse {
+    pub games: Vec<Game>,
+}
+
+/// Per-game `manifest.json` shipped inside an installed game's folder
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    pub title: String,
+    /// Program to run, relative to the game folder
+    pub executable: String,
+    pub args: Vec<String>,
+    /// Directory to run from, relative to the game folder; the folder itself if unset
+    pub working_dir: Option<String>,
+    /// Checksum of the archive this copy was installed from, written by the loader
+    pub checksum: Option<String>,
+    /// Server version this copy was installed from, written by the loader
+    pub version: Option<String>,
+    /// SHA-256 of every installed file by relative path, written by the loader
+    #[serde(skip_serializing)]
+    pub files: BTreeMap<String, String>,
+}
+
+/// A game found in the local games directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledGame {
+    pub id: String,
+    pub title: String,
+    pub install_path: PathBuf,
+    pub size_on_disk: u64,
+    /// Parsed `manifest.json`, if the game has a valid one
+    pub manifest: Option<Manifest>,
+    /// Whether the manifest names an executable inside the game folder
+    pub launchable: bool,
+}
+
+/// An installed game with a newer build on the server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub id: String,
+    pub title: String,
+    pub installed_checksum: Option<String>,
+    pub installed_version: Option<String>,
+    pub latest_checksum: Option<String>,
+    pub latest_version: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn games_response_deserializes_sample_payload() {
+        let payload = r#"{
+            "games": [
+                {
+                    "id": "g1",
+                    "date": "2024-05-01",
+                    "title": "Rocket Run",
+                    "sizeBytes": 1048576,
+                    "checksum": "abc123",
+                    "genre": "arcade"
+                },
+                { "id": "g2", "date": "2024-05-02", "title": "Maze" }
+            ],
+            "total": 2
+        }"#;
+        let response: GamesResponse = serde_json::from_str(payload).unwrap();
+        assert_eq!(
+            response.games,
+            vec![
+                Game {
+                    id: String::from("g1"),
+                    date: String::from("2024-05-01"),
+                    title: String::from("Rocket Run"),
+                    size_bytes: 1_048_576,
+                    checksum: Some(String::from("abc123")),
+                    ..Game::default()
+                },
+                Game {
+                    id: String::from("g2"),
+                    date: String::from("2024-05-02"),
+                    title: String::from("Maze"),
+                    ..Game::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn game_list_round_trips_as_camel_case() {
+        let games = vec![Game {
+            id: String::from("g1"),
+            size_bytes: 7,
+            ..Game::default()
+        }];
+        let json = serde_json::to_value(&games).unwrap();
+        assert_eq!(json[0]["sizeBytes"], 7);
+        let back: Vec<Game> = serde_json::from_value(json).unwrap();
+        assert_eq!(back, games);
+    }
+}
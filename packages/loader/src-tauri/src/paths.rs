@@ -0,0 +1,293 @@
+// On-disk layout of installed and in-progress games
+
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::error::SdrError;
+
+/// Directory under the platform data dir that holds everything the loader stores
+const APP_DIR: &str = "sdr";
+
+/// Platform-specific games directory, created if missing
+///
+/// `$XDG_DATA_HOME/sdr/games` (or `~/.local/share/sdr/games`) on Linux and the
+/// Steam Deck, `%APPDATA%\sdr\games` on Windows and
+/// `~/Library/Application Support/sdr/games` on macOS.
+pub fn default_games_dir() -> Result<PathBuf, SdrError> {
+    let dir = default_games_path()?;
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", dir.display())))?;
+    Ok(dir)
+}
+
+/// Where `default_games_dir` lives, whether or not it exists yet
+fn default_games_path() -> Result<PathBuf, SdrError> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| SdrError::Io(String::from("Could not determine the data directory")))?;
+    let dir = data_dir.join(APP_DIR).join("games");
+    std::path::absolute(&dir)
+        .map_err(|e| SdrError::Io(format!("Failed to resolve {}: {e}", dir.display())))
+}
+
+/// Games directory to use for `config`
+///
+/// Honors the configured override when it is writable, otherwise warns and falls
+/// back to the platform default.
+pub fn games_dir(config: &Config) -> Result<PathBuf, SdrError> {
+    if let Some(dir) = &config.games_dir {
+        match ensure_writable(dir) {
+            Ok(()) => {
+                return std::path::absolute(dir)
+                    .map_err(|e| SdrError::Io(format!("Failed to resolve {}: {e}", dir.display())))
+            }
+            Err(e) => tracing::warn!("{e}, using the default games directory"),
+        }
+    }
+    default_games_dir()
+}
+
+/// Games directory `games_dir` would pick for `config`, without touching the disk
+///
+/// For dry runs: nothing is created, and the configured override is taken when
+/// it is a directory not marked read-only instead of probing it with a write.
+pub fn planned_games_dir(config: &Config) -> Result<PathBuf, SdrError> {
+    if let Some(dir) = &config.games_dir {
+        let usable = std::fs::metadata(dir)
+            .is_ok_and(|metadata| metadata.is_dir() && !metadata.permissions().readonly());
+        if usable {
+            return std::path::absolute(dir)
+                .map_err(|e| SdrError::Io(format!("Failed to resolve {}: {e}", dir.display())));
+        }
+    }
+    default_games_path()
+}
+
+/// Loader config directory, created if missing
+pub fn config_dir() -> Result<PathBuf, SdrError> {
+    let config_dir = dirs::config_dir()
+        .ok_or_else(|| SdrError::Io(String::from("Could not determine the config directory")))?;
+    let dir = config_dir.join(APP_DIR);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", dir.display())))?;
+    Ok(dir)
+}
+
+/// Where log files are written, created if missing
+pub fn log_dir() -> Result<PathBuf, SdrError> {
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| SdrError::Io(String::from("Could not determine the data directory")))?;
+    let dir = data_dir.join(APP_DIR).join("logs");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", dir.display())))?;
+    Ok(dir)
+}
+
+/// Where downloaded thumbnails are cached
+///
+/// `thumbnails/` under the platform cache dir, so the OS may clear it.
+pub fn thumbnails_dir() -> Result<PathBuf, SdrError> {
+    let cache_dir = dirs::cache_dir()
+        .ok_or_else(|| SdrError::Io(String::from("Could not determine the cache directory")))?;
+    Ok(cache_dir.join(APP_DIR).join("thumbnails"))
+}
+
+/// Check that `dir` is an existing directory files can be written inside
+///
+/// A missing directory is an error rather than created, so a typo or an
+/// unmounted drive in the config doesn't quietly become a new folder.
+pub fn ensure_writable(dir: &Path) -> Result<(), SdrError> {
+    if !dir.is_dir() {
+        return Err(SdrError::NotFound(format!(
+            "{} does not exist or is not a directory",
+            dir.display()
+        )));
+    }
+    let unwritable =
+        |e: std::io::Error| SdrError::Io(format!("{} is not writable: {e}", dir.display()));
+    let probe = dir.join(".sdr-write-test");
+    std::fs::write(&probe, b"").map_err(unwritable)?;
+    let _ = std::fs::remove_file(probe);
+    Ok(())
+}
+
+/// Reject ids that could escape the games directory when used as a path component
+pub fn check_game_date(game_date: &str) -> Result<(), SdrError> {
+    let valid = !game_date.is_empty()
+        && !game_date.starts_with('.')
+        && game_date
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(SdrError::Invalid(format!("Invalid game id: {game_date:?}")))
+    }
+}
+
+/// Where a game's downloaded archive is written
+pub fn archive_path(games_dir: &Path, game_date: &str) -> PathBuf {
+    games_dir.join(format!("{game_date}.zip"))
+}
+
+/// Where an archive is streamed to until the download completes
+pub fn part_path(games_dir: &Path, game_date: &str) -> PathBuf {
+    games_dir.join(format!("{game_date}.zip.part"))
+}
+
+/// Where a game is installed
+pub fn game_dir(games_dir: &Path, game_date: &str) -> PathBuf {
+    games_dir.join(game_date)
+}
+
+/// Scratch directory an archive is unpacked into before it replaces the install
+///
+/// Hidden, so it can never clash with a valid game id.
+pub fn staging_dir(games_dir: &Path, game_date: &str) -> PathBuf {
+    games_dir.join(format!(".{game_date}.extracting"))
+}
+
+/// Where a folder being imported waits before it replaces the install
+///
+/// Kept apart from `staging_dir`, since a moved folder holds the user's only copy.
+pub fn import_staging_dir(games_dir: &Path, game_date: &str) -> PathBuf {
+    games_dir.join(format!(".{game_date}.importing"))
+}
+
+/// Where an install being replaced by an import is kept until the new one is in place
+pub fn replaced_install_dir(games_dir: &Path, game_date: &str) -> PathBuf {
+    games_dir.join(format!(".{game_date}.old"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// Held by tests that change environment variables
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn default_games_dir_lives_under_xdg_data_home() {
+        let _env = ENV_LOCK.lock().unwrap();
+        let data_home = tempfile::tempdir().unwrap();
+        let previous = std::env::var_os("XDG_DATA_HOME");
+        std::env::set_var("XDG_DATA_HOME", data_home.path());
+
+        let dir = default_games_dir();
+
+        match previous {
+            Some(value) => std::env::set_var("XDG_DATA_HOME", value),
+            None => std::env::remove_var("XDG_DATA_HOME"),
+        }
+        let dir = dir.unwrap();
+        assert_eq!(dir, data_home.path().join("sdr").join("games"));
+        assert!(dir.is_dir());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn the_planned_games_dir_is_not_created_or_probed() {
+        let _env = ENV_LOCK.lock().unwrap();
+        let (data_home, configured) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let previous = std::env::var_os("XDG_DATA_HOME");
+        std::env::set_var("XDG_DATA_HOME", data_home.path());
+        let config = |games_dir: PathBuf| Config {
+            games_dir: Some(games_dir),
+            ..Config::default()
+        };
+
+        let chosen = planned_games_dir(&config(configured.path().to_path_buf()));
+        let fallback = planned_games_dir(&config(configured.path().join("missing")));
+
+        match previous {
+            Some(value) => std::env::set_var("XDG_DATA_HOME", value),
+            None => std::env::remove_var("XDG_DATA_HOME"),
+        }
+        assert_eq!(chosen.unwrap(), configured.path());
+        assert_eq!(
+            fallback.unwrap(),
+            data_home.path().join("sdr").join("games")
+        );
+        assert_eq!(std::fs::read_dir(data_home.path()).unwrap().count(), 0);
+        assert_eq!(std::fs::read_dir(configured.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn an_existing_games_dir_is_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        ensure_writable(dir.path()).unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn a_missing_games_dir_is_not_created() {
+        let parent = tempfile::tempdir().unwrap();
+        let dir = parent.path().join("missing");
+
+        assert_eq!(ensure_writable(&dir).unwrap_err().kind(), "NotFound");
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn a_file_is_not_a_usable_games_dir() {
+        let parent = tempfile::tempdir().unwrap();
+        let file = parent.path().join("games");
+        std::fs::write(&file, b"").unwrap();
+
+        assert_eq!(ensure_writable(&file).unwrap_err().kind(), "NotFound");
+    }
+
+    /// Runs `f` with `XDG_DATA_HOME` pointed at `data_home`, restoring it after
+    #[cfg(target_os = "linux")]
+    fn with_data_home<T>(data_home: &Path, f: impl FnOnce() -> T) -> T {
+        let _env = ENV_LOCK.lock().unwrap();
+        let previous = std::env::var_os("XDG_DATA_HOME");
+        std::env::set_var("XDG_DATA_HOME", data_home);
+        let result = f();
+        match previous {
+            Some(value) => std::env::set_var("XDG_DATA_HOME", value),
+            None => std::env::remove_var("XDG_DATA_HOME"),
+        }
+        result
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn a_writable_games_dir_override_is_used() {
+        let (data_home, configured) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let config = Config {
+            games_dir: Some(configured.path().to_path_buf()),
+            ..Config::default()
+        };
+
+        let dir = with_data_home(data_home.path(), || games_dir(&config)).unwrap();
+
+        assert_eq!(dir, configured.path());
+        assert_eq!(std::fs::read_dir(data_home.path()).unwrap().count(), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn an_unusable_games_dir_override_falls_back_to_the_default() {
+        let (data_home, parent) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let file = parent.path().join("games");
+        std::fs::write(&file, b"").unwrap();
+        let config = |games_dir: PathBuf| Config {
+            games_dir: Some(games_dir),
+            ..Config::default()
+        };
+
+        let missing = with_data_home(data_home.path(), || {
+            games_dir(&config(parent.path().join("missing")))
+        });
+        let not_a_dir = with_data_home(data_home.path(), || games_dir(&config(file.clone())));
+
+        let default = data_home.path().join("sdr").join("games");
+        assert_eq!(missing.unwrap(), default);
+        assert_eq!(not_a_dir.unwrap(), default);
+        assert!(default.is_dir());
+        assert!(!parent.path().join("missing").exists());
+    }
+}
@@ -0,0 +1,187 @@
+// Crash-safe reads and writes of the loader's state files
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::SdrError;
+
+/// Write `value` to `path` as pretty JSON, atomically
+pub fn save_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), SdrError> {
+    let text = serde_json::to_string_pretty(value)
+        .map_err(|e| SdrError::Parse(format!("Failed to serialize {}: {e}", path.display())))?;
+    write_atomic(path, text.as_bytes())
+}
+
+/// Read the JSON at `path`, or the default if it's missing or corrupt
+///
+/// A corrupt file is moved to `<path>.bak` first, so it can be looked at later
+/// and the next save doesn't overwrite the only copy.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    load_with(path, |text| {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    })
+}
+
+/// Held while a state file is read, changed and written back
+static UPDATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Numbers the temporary files, so two writes of one file never share one
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Apply `change` to the JSON at `path`, read like `load_json`, and save it
+///
+/// Updates are serialized, so two commands changing the same file at once
+/// can't drop one another's change.
+pub fn update_json<T, R>(path: &Path, change: impl FnOnce(&mut T) -> R) -> Result<R, SdrError>
+where
+    T: Serialize + DeserializeOwned + Default,
+{
+    let _guard = lock_updates();
+    let mut value = load_json(path);
+    let result = change(&mut value);
+    save_json(path, &value)?;
+    Ok(result)
+}
+
+/// Keep `update_json` calls waiting, for a change spanning several files
+pub fn lock_updates() -> MutexGuard<'static, ()> {
+    UPDATE_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Write `value` to `path` as TOML, atomically
+pub fn save_toml<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), SdrError> {
+    let text = toml::to_string_pretty(value)
+        .map_err(|e| SdrError::Parse(format!("Failed to serialize {}: {e}", path.display())))?;
+    write_atomic(path, text.as_bytes())
+}
+
+/// Read the TOML at `path`, recovering from corruption like `load_json`
+pub fn load_toml<T: DeserializeOwned + Default>(path: &Path) -> T {
+    load_with(path, |text| toml::from_str(text).map_err(|e| e.to_string()))
+}
+
+/// Write `contents` to a temporary file next to `path`, then rename it over `path`
+///
+/// The data is flushed to disk before the rename, so a crash or power loss
+/// mid-write leaves the previous file intact instead of a truncated one. Each
+/// write gets its own temporary file, so concurrent writes can't mix.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), SdrError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", dir.display())))?;
+    }
+    let temp = with_suffix(
+        path,
+        &format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ),
+    );
+    let written = std::fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .map_err(|e| SdrError::Io(format!("Failed to write {}: {e}", temp.display())));
+    let result = written.and_then(|()| {
+        std::fs::rename(&temp, path)
+            .map_err(|e| SdrError::Io(format!("Failed to replace {}: {e}", path.display())))
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+fn load_with<T: Default>(path: &Path, parse: impl FnOnce(&str) -> Result<T, String>) -> T {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return T::default(),
+        Err(e) => {
+            tracing::warn!("failed to read {}, using defaults: {e}", path.display());
+            return T::default();
+        }
+    };
+    parse(&text).unwrap_or_else(|e| {
+        let backup = with_suffix(path, ".bak");
+        match std::fs::rename(path, &backup) {
+            Ok(()) => tracing::warn!(
+                "ignoring invalid {}, moved it to {}: {e}",
+                path.display(),
+                backup.display()
+            ),
+            Err(rename_error) => tracing::warn!(
+                "ignoring invalid {} and failed to back it up ({rename_error}): {e}",
+                path.display()
+            ),
+        }
+        T::default()
+    })
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    #[test]
+    fn a_corrupt_file_is_backed_up_and_replaced_by_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("favorites.json");
+        std::fs::write(&path, b"{ not json").unwrap();
+
+        let loaded: BTreeSet<String> = load_json(&path);
+
+        assert!(loaded.is_empty());
+        assert!(!path.exists());
+        let backup = with_suffix(&path, ".bak");
+        assert_eq!(std::fs::read(&backup).unwrap(), b"{ not json");
+
+        save_json(&path, &BTreeSet::from(["2024-01-01"])).unwrap();
+        let reloaded: BTreeSet<String> = load_json(&path);
+        assert_eq!(reloaded, BTreeSet::from([String::from("2024-01-01")]));
+        assert_eq!(std::fs::read(&backup).unwrap(), b"{ not json");
+    }
+
+    #[test]
+    fn concurrent_updates_are_all_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.json");
+
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    for i in 0..20 {
+                        update_json(path, |values: &mut BTreeSet<u32>| {
+                            values.insert(thread * 100 + i)
+                        })
+                        .unwrap();
+                    }
+                });
+            }
+        });
+
+        let values: BTreeSet<u32> = load_json(&path);
+        assert_eq!(values.len(), 8 * 20);
+        let files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, ["history.json"], "temporary files were left behind");
+    }
+}
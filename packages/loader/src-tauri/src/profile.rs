@@ -0,0 +1,208 @@
+// Sharing favorites, history and settings as a single file
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error::SdrError;
+use crate::favorites::Favorites;
+use crate::persist;
+use crate::randomizer::{History, HISTORY_LEN};
+
+/// Format version written by `export_profile`, bumped on incompatible changes
+pub const PROFILE_VERSION: u32 = 1;
+
+/// Contents of an exported profile file
+///
+/// Only `version` is required; fields added by newer loaders are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub version: u32,
+    #[serde(default)]
+    pub favorites: Favorites,
+    #[serde(default)]
+    pub history: History,
+    /// Shareable settings, see `Config::shareable`
+    #[serde(default)]
+    pub config: Config,
+}
+
+impl Profile {
+    pub fn new(favorites: Favorites, history: History, config: Config) -> Self {
+        Self {
+            version: PROFILE_VERSION,
+            favorites,
+            history,
+            config: config.shareable(),
+        }
+    }
+}
+
+pub fn export_profile(path: &Path, profile: &Profile) -> Result<(), SdrError> {
+    persist::save_json(path, profile)
+}
+
+/// Read a profile, refusing ones written by a newer, incompatible loader
+pub fn read_profile(path: &Path) -> Result<Profile, SdrError> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| SdrError::Io(format!("Failed to read {}: {e}", path.display())))?;
+    let profile: Profile = serde_json::from_str(&text)
+        .map_err(|e| SdrError::Parse(format!("Invalid profile {}: {e}", path.display())))?;
+    if profile.version == 0 || profile.version > PROFILE_VERSION {
+        return Err(SdrError::Invalid(format!(
+            "Profile {} has version {}, this loader reads up to version {PROFILE_VERSION}",
+            path.display(),
+            profile.version
+        )));
+    }
+    Ok(profile)
+}
+
+/// Combine an imported profile with the local state
+///
+/// With `replace`, the imported favorites, history and settings win outright.
+/// Otherwise favorites are unioned, history entries are interleaved by time, and
+/// only settings unset locally are taken. Either way the API key, mirrors and
+/// the other settings `Config::shareable` drops stay as they are locally.
+pub fn merge_profile(
+    favorites: &mut Favorites,
+    history: &mut History,
+    config: &mut Config,
+    imported: Profile,
+    replace: bool,
+) {
+    if replace {
+        *favorites = imported.favorites;
+        *history = imported.history;
+        config.replace_preferences(imported.config);
+        return;
+    }
+
+    favorites.game_dates.extend(imported.favorites.game_dates);
+    for entry in imported.history.entries {
+        if !history.entries.contains(&entry) {
+            history.entries.push(entry);
+        }
+    }
+    history.entries.sort_by_key(|entry| entry.picked_at);
+    let excess = history.entries.len().saturating_sub(HISTORY_LEN);
+    history.entries.drain(..excess);
+    config.merge_missing(imported.config);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// A config with every setting filled in
+    fn full_config() -> Config {
+        Config {
+            games_dir: Some(PathBuf::from("/media/sd/games")),
+            fetch_attempts: Some(4),
+            mirrors: vec![String::from("https://games.example")],
+            api_key: Some(String::from("secret")),
+            auto_sync: Some(false),
+            sync_interval_mins: Some(15),
+            max_download_bytes_per_sec: Some(1_000_000),
+            request_timeout_secs: Some(20),
+            connect_timeout_secs: Some(5),
+            cert_pin: Some("ab".repeat(32)),
+            max_parallel_downloads: Some(3),
+            speed_window_secs: Some(8),
+            max_requests_per_minute: Some(30),
+            proxy_url: Some(String::from("http://proxy.example:3128")),
+            ws_url: Some(String::from("wss://games.example/ws")),
+        }
+    }
+
+    /// What a fresh install on another machine has configured
+    fn other_machine() -> Config {
+        Config {
+            mirrors: vec![String::from("https://other.example")],
+            api_key: Some(String::from("other key")),
+            ..Config::default()
+        }
+    }
+
+    /// The settings a profile must never change
+    fn trust_settings(config: &Config) -> Config {
+        Config {
+            games_dir: config.games_dir.clone(),
+            mirrors: config.mirrors.clone(),
+            api_key: config.api_key.clone(),
+            cert_pin: config.cert_pin.clone(),
+            proxy_url: config.proxy_url.clone(),
+            ws_url: config.ws_url.clone(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn an_exported_profile_restores_preferences_but_not_trust_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile.json");
+        let mut favorites = Favorites::default();
+        favorites.add("2024-01-01");
+        let mut history = History::default();
+        history.record("2024-01-02");
+        let config = full_config();
+        export_profile(
+            &path,
+            &Profile::new(favorites.clone(), history.clone(), config.clone()),
+        )
+        .unwrap();
+
+        for replace in [false, true] {
+            let (mut local_favorites, mut local_history) =
+                (Favorites::default(), History::default());
+            let mut local_config = other_machine();
+
+            merge_profile(
+                &mut local_favorites,
+                &mut local_history,
+                &mut local_config,
+                read_profile(&path).unwrap(),
+                replace,
+            );
+
+            assert_eq!(local_favorites, favorites);
+            assert_eq!(local_history.entries, history.entries);
+            assert_eq!(trust_settings(&local_config), other_machine());
+            assert_eq!(local_config.shareable(), config.clone().shareable());
+        }
+    }
+
+    #[test]
+    fn an_edited_profile_cant_change_trust_settings() {
+        let edited = Profile {
+            version: PROFILE_VERSION,
+            favorites: Favorites::default(),
+            history: History::default(),
+            config: full_config(),
+        };
+
+        for replace in [false, true] {
+            let mut local_config = Config::default();
+            merge_profile(
+                &mut Favorites::default(),
+                &mut History::default(),
+                &mut local_config,
+                edited.clone(),
+                replace,
+            );
+
+            assert_eq!(trust_settings(&local_config), Config::default());
+            assert_eq!(local_config.fetch_attempts, Some(4));
+        }
+    }
+
+    #[test]
+    fn an_exported_profile_holds_no_trust_settings() {
+        let shared = full_config().shareable();
+        assert_eq!(trust_settings(&shared), Config::default());
+        assert_eq!(shared.speed_window_secs, Some(8));
+    }
+}
@@ -0,0 +1,188 @@
+// Making room by uninstalling games nobody plays anymore
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::disk;
+use crate::error::SdrError;
+use crate::favorites::Favorites;
+use crate::install::{self, MANIFEST_FILE};
+use crate::models::InstalledGame;
+use crate::stats::Stats;
+
+/// What `prune_to_free` removed, or would remove on a dry run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneReport {
+    /// Game dates, in the order they were removed
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+    /// Free space afterwards; estimated from game sizes on a dry run
+    pub free_bytes: u64,
+    pub target_reached: bool,
+    pub dry_run: bool,
+}
+
+/// Uninstall the least recently played games until `target_free_bytes` are free
+///
+/// Games go in the order they were last played, or for never played ones
+/// installed, the longest ago first.
+/// Favorites are never removed, nor games `is_busy` reports as downloading or
+/// running. A game that fails to uninstall is skipped with a warning.
+pub fn prune_to_free(
+    games_dir: &Path,
+    target_free_bytes: u64,
+    stats: &Stats,
+    favorites: &Favorites,
+    is_busy: impl Fn(&str) -> bool,
+    dry_run: bool,
+) -> Result<PruneReport, SdrError> {
+    let mut report = PruneReport {
+        free_bytes: disk::free_space(existing_ancestor(games_dir))?,
+        dry_run,
+        ..PruneReport::default()
+    };
+    if report.free_bytes >= target_free_bytes {
+        report.target_reached = true;
+        return Ok(report);
+    }
+
+    for game in candidates(games_dir, stats, favorites, &is_busy)? {
+        // Checked again, a download or launch may have started in the meantime
+        if is_busy(&game.id) {
+            continue;
+        }
+        if dry_run {
+            report.freed_bytes += game.size_on_disk;
+            report.free_bytes += game.size_on_disk;
+        } else {
+            match install::uninstall_game(games_dir, &game.id) {
+                Ok(freed) => report.freed_bytes += freed,
+                Err(e) => {
+                    tracing::warn!("couldn't prune {}: {e}", game.id);
+                    continue;
+                }
+            }
+            report.free_bytes = disk::free_space(games_dir)?;
+        }
+        tracing::info!(game_date = %game.id, dry_run, "pruned game");
+        report.removed.push(game.id);
+        if report.free_bytes >= target_free_bytes {
+            report.target_reached = true;
+            break;
+        }
+    }
+    Ok(report)
+}
+
+/// Installed games that may be pruned, the first to go first
+fn candidates(
+    games_dir: &Path,
+    stats: &Stats,
+    favorites: &Favorites,
+    is_busy: impl Fn(&str) -> bool,
+) -> Result<Vec<InstalledGame>, SdrError> {
+    let last_used = |game: &InstalledGame| -> Option<DateTime<Utc>> {
+        stats
+            .games
+            .get(&game.id)
+            .and_then(|entry| entry.last_played)
+            .or_else(|| installed_at(&game.install_path))
+    };
+    let mut games: Vec<InstalledGame> = install::list_installed_games(games_dir)?
+        .into_iter()
+        .filter(|game| !favorites.contains(&game.id) && !is_busy(&game.id))
+        .collect();
+    // A game with no known time at all sorts first, then the game date decides ties
+    games.sort_by_cached_key(|game| (last_used(game), game.id.clone()));
+    Ok(games)
+}
+
+/// When the game in `dir` was installed, going by its manifest or else the folder
+fn installed_at(dir: &Path) -> Option<DateTime<Utc>> {
+    std::fs::metadata(dir.join(MANIFEST_FILE))
+        .or_else(|_| std::fs::metadata(dir))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .map(DateTime::from)
+}
+
+/// `path` or its closest parent that exists, for asking about free space
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors().find(|dir| dir.exists()).unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::paths;
+    use crate::stats::GameStats;
+
+    /// Install `game_date` with its manifest written `days_ago`
+    fn install_at(games_dir: &Path, game_date: &str, days_ago: u64) {
+        let dir = paths::game_dir(games_dir, game_date);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("index.html"), [0; 100]).unwrap();
+        let manifest = dir.join(MANIFEST_FILE);
+        std::fs::write(&manifest, b"{}").unwrap();
+        let written = SystemTime::now() - std::time::Duration::from_secs(days_ago * 24 * 3600);
+        std::fs::File::options()
+            .write(true)
+            .open(&manifest)
+            .unwrap()
+            .set_modified(written)
+            .unwrap();
+    }
+
+    fn played(stats: &mut Stats, game_date: &str, days_ago: i64) {
+        stats.games.insert(
+            game_date.to_string(),
+            GameStats {
+                game_date: game_date.to_string(),
+                play_count: 1,
+                total_play_secs: 60,
+                last_played: Some(Utc::now() - TimeDelta::days(days_ago)),
+            },
+        );
+    }
+
+    #[test]
+    fn never_played_games_go_by_install_time() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let mut stats = Stats::default();
+        install_at(games_dir.path(), "2024-01-01", 30);
+        played(&mut stats, "2024-01-01", 3);
+        install_at(games_dir.path(), "2024-01-02", 10);
+        install_at(games_dir.path(), "2024-01-03", 1);
+        install_at(games_dir.path(), "2024-01-04", 30);
+        played(&mut stats, "2024-01-04", 20);
+        install_at(games_dir.path(), "2024-01-05", 40);
+        install_at(games_dir.path(), "2024-01-06", 50);
+        let mut favorites = Favorites::default();
+        favorites.add("2024-01-05");
+
+        let report = prune_to_free(
+            games_dir.path(),
+            u64::MAX,
+            &stats,
+            &favorites,
+            |game_date| game_date == "2024-01-06",
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.removed,
+            ["2024-01-04", "2024-01-02", "2024-01-01", "2024-01-03"]
+        );
+        assert!(!report.target_reached);
+        assert_eq!(report.freed_bytes, 4 * 102);
+        assert!(paths::game_dir(games_dir.path(), "2024-01-04").is_dir());
+    }
+}
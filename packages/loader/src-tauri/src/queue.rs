@@ -0,0 +1,581 @@
+// Download queue with bounded parallelism
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::{watch, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::SdrError;
+
+/// Downloads allowed to run at the same time unless overridden
+pub const DEFAULT_MAX_PARALLEL: usize = 3;
+
+/// Lifecycle of a queued download
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DownloadState {
+    Queued,
+    Downloading,
+    /// Waiting for `resume_queue`, with any partial download kept
+    Paused,
+    Done,
+    Failed,
+}
+
+/// One entry as reported by `queue_status`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueItem {
+    pub game_date: String,
+    pub state: DownloadState,
+    pub error: Option<SdrError>,
+}
+
+/// Runs enqueued downloads, at most `max_parallel` at a time
+///
+/// Held in Tauri managed state; clones share the same queue.
+#[derive(Clone)]
+pub struct DownloadQueue {
+    items: Arc<Mutex<Vec<QueueItem>>>,
+    permits: Arc<Semaphore>,
+    paused: Arc<watch::Sender<bool>>,
+    /// Handed to each job run, cancelled when the queue is paused
+    pause_token: Arc<Mutex<CancellationToken>>,
+}
+
+impl DownloadQueue {
+    pub fn new(max_parallel: usize) -> Self {
+        Self {
+            items: Arc::new(Mutex::new(Vec::new())),
+            permits: Arc::new(Semaphore::new(max_parallel.max(1))),
+            paused: Arc::new(watch::Sender::new(false)),
+            pause_token: Arc::new(Mutex::new(CancellationToken::new())),
+        }
+    }
+
+    /// Queue `job` to download `game_date`
+    ///
+    /// Returns `false` without doing anything if that game is already queued or
+    /// downloading. A failed job is recorded as `Failed` and doesn't affect the rest.
+    /// `job` gets a token that is cancelled when the queue is paused; it should then
+    /// keep its partial download and return `SdrError::Paused`, and is run again
+    /// on `resume`. Cancelling `cancel` while the download waits for a slot or for
+    /// `resume` runs `job` right away, so it can clean up and report the
+    /// cancellation like it does for a running download.
+    pub fn enqueue<F, Fut>(&self, game_date: &str, cancel: CancellationToken, job: F) -> bool
+    where
+        F: Fn(CancellationToken) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), SdrError>> + Send + 'static,
+    {
+        {
+            let mut items = self.items.lock().unwrap();
+            match items.iter_mut().find(|item| item.game_date == game_date) {
+                Some(item)
+                    if matches!(
+                        item.state,
+                        DownloadState::Queued | DownloadState::Downloading | DownloadState::Paused
+                    ) =>
+                {
+                    return false;
+                }
+                Some(item) => {
+                    item.state = self.waiting_state();
+                    item.error = None;
+                }
+                None => items.push(QueueItem {
+                    game_date: game_date.to_string(),
+                    state: self.waiting_state(),
+                    error: None,
+                }),
+            }
+        }
+
+        let queue = self.clone();
+        let game_date = game_date.to_string();
+        tauri::async_runtime::spawn(async move {
+            let mut paused = queue.paused.subscribe();
+            loop {
+                let slot = async {
+                    paused.wait_for(|paused| !paused).await.ok()?;
+                    queue.permits.clone().acquire_owned().await.ok()
+                };
+                let permit = tokio::select! {
+                    biased;
+                    () = cancel.cancelled() => None,
+                    permit = slot => match permit {
+                        Some(permit) => Some(permit),
+                        None => return,
+                    },
+                };
+                let pause = queue.pause_token.lock().unwrap().clone();
+                if permit.is_some() && pause.is_cancelled() {
+                    // Paused while waiting for a slot
+                    continue;
+                }
+                queue.set_state(&game_date, DownloadState::Downloading, None);
+                match job(pause).await {
+                    Ok(()) => queue.set_state(&game_date, DownloadState::Done, None),
+                    Err(SdrError::Paused(_)) => {
+                        queue.set_state(&game_date, DownloadState::Paused, None);
+                        continue;
+                    }
+                    Err(error) => queue.set_state(&game_date, DownloadState::Failed, Some(error)),
+                }
+                return;
+            }
+        });
+        true
+    }
+
+    /// Stop starting downloads and halt running ones, keeping their partial files
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+        self.pause_token.lock().unwrap().cancel();
+        for item in self.items.lock().unwrap().iter_mut() {
+            if item.state == DownloadState::Queued {
+                item.state = DownloadState::Paused;
+            }
+        }
+    }
+
+    /// Pick paused downloads back up, resuming from their partial files
+    pub fn resume(&self) {
+        *self.pause_token.lock().unwrap() = CancellationToken::new();
+        for item in self.items.lock().unwrap().iter_mut() {
+            if item.state == DownloadState::Paused {
+                item.state = DownloadState::Queued;
+            }
+        }
+        self.paused.send_replace(false);
+    }
+
+    /// State a newly queued download starts in
+    fn waiting_state(&self) -> DownloadState {
+        if *self.paused.borrow() {
+            DownloadState::Paused
+        } else {
+            DownloadState::Queued
+        }
+    }
+
+    /// Snapshot of every download the queue knows about, in enqueue order
+    pub fn status(&self) -> Vec<QueueItem> {
+        self.items.lock().unwrap().clone()
+    }
+
+    fn set_state(&self, game_date: &str, state: DownloadState, error: Option<SdrError>) {
+        let mut items = self.items.lock().unwrap();
+        if let Some(item) = items.iter_mut().find(|item| item.game_date == game_date) {
+            item.state = state;
+            item.error = error;
+        }
+    }
+}
+
+impl Default for DownloadQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_PARALLEL)
+    }
+}
+
+/// Payload of the `batch-complete` event
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchSummary {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<BatchFailure>,
+    /// Already installed or already in the queue
+    pub skipped: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchFailure {
+    pub game_date: String,
+    pub error: SdrError,
+}
+
+/// Collects the outcomes of a batch of queued downloads
+///
+/// The summary is handed out exactly once: by `seal` if every download already
+/// ended, otherwise by the `finish` call for the last one to end.
+#[derive(Default)]
+pub struct Batch {
+    state: Mutex<BatchState>,
+}
+
+#[derive(Default)]
+struct BatchState {
+    summary: BatchSummary,
+    pending: usize,
+    sealed: bool,
+}
+
+impl Batch {
+    /// The games of `dates` to download, each once and in order
+    ///
+    /// Repeats are dropped, and games `skip` returns `true` for, like installed
+    /// ones, are noted as skipped.
+    pub fn select(&self, dates: Vec<String>, skip: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut selected: Vec<String> = Vec::new();
+        for game_date in dates {
+            if selected.contains(&game_date) || self.is_skipped(&game_date) {
+                continue;
+            }
+            if skip(&game_date) {
+                self.skip(&game_date);
+            } else {
+                selected.push(game_date);
+            }
+        }
+        selected
+    }
+
+    fn is_skipped(&self, game_date: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .summary
+            .skipped
+            .iter()
+            .any(|skipped| skipped == game_date)
+    }
+
+    /// Expect one more download to `finish`
+    pub fn add(&self) {
+        self.state.lock().unwrap().pending += 1;
+    }
+
+    /// Note a game that wasn't downloaded
+    pub fn skip(&self, game_date: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.summary.skipped.push(game_date.to_string());
+    }
+
+    /// Take back an `add` for a game that turned out to be skipped
+    pub fn skip_added(&self, game_date: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.pending -= 1;
+        state.summary.skipped.push(game_date.to_string());
+    }
+
+    /// Record how one download ended, returning the summary if it was the last
+    pub fn finish(&self, game_date: &str, result: &Result<(), SdrError>) -> Option<BatchSummary> {
+        let mut state = self.state.lock().unwrap();
+        match result {
+            Ok(()) => state.summary.succeeded.push(game_date.to_string()),
+            Err(error) => state.summary.failed.push(BatchFailure {
+                game_date: game_date.to_string(),
+                error: error.clone(),
+            }),
+        }
+        state.pending -= 1;
+        Self::complete(&mut state)
+    }
+
+    /// Mark the batch as fully enqueued, returning the summary if nothing is pending
+    pub fn seal(&self) -> Option<BatchSummary> {
+        let mut state = self.state.lock().unwrap();
+        state.sealed = true;
+        Self::complete(&mut state)
+    }
+
+    fn complete(state: &mut BatchState) -> Option<BatchSummary> {
+        (state.sealed && state.pending == 0).then(|| std::mem::take(&mut state.summary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use futures_util::future::BoxFuture;
+
+    use super::*;
+    use crate::api::ApiClient;
+    use crate::config::ServerConfig;
+    use crate::download;
+    use crate::models::Game;
+    use crate::paths;
+    use crate::test_support::{self, MockServer, RecordingEvents, Response};
+
+    /// Poll `queue` until nothing is waiting or running, returning the most
+    /// downloads seen in `Downloading` at once
+    async fn wait_until_settled(queue: &DownloadQueue) -> usize {
+        let mut most_downloading = 0;
+        loop {
+            let status = queue.status();
+            let downloading = status
+                .iter()
+                .filter(|item| item.state == DownloadState::Downloading)
+                .count();
+            most_downloading = most_downloading.max(downloading);
+            if status
+                .iter()
+                .all(|item| matches!(item.state, DownloadState::Done | DownloadState::Failed))
+            {
+                return most_downloading;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    /// Poll `queue` until the download of `game_date` is in `state`
+    async fn wait_for_state(queue: &DownloadQueue, game_date: &str, state: DownloadState) {
+        while !queue
+            .status()
+            .iter()
+            .any(|item| item.game_date == game_date && item.state == state)
+        {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    /// A paused download of `game` in progress, with its `.part` file partly written
+    struct PausedDownload {
+        server: MockServer,
+        queue: DownloadQueue,
+        games_dir: tempfile::TempDir,
+        game: Game,
+        cancel: CancellationToken,
+        events: Arc<RecordingEvents>,
+        archive_len: u64,
+    }
+
+    impl PausedDownload {
+        /// Queue a download served in slow chunks and pause the queue once it started
+        async fn start() -> Self {
+            let archive = test_support::sample_archive();
+            let archive_len = archive.len() as u64;
+            let server = MockServer::start(move |request| {
+                test_support::archive_response(&archive, request)
+                    .slow(64 * 1024, Duration::from_millis(20))
+            })
+            .await;
+            let api = Arc::new(ApiClient::new(ServerConfig::new([&server.url])));
+            let games_dir = tempfile::tempdir().unwrap();
+            let game = test_support::game("g1", "2024-03-01");
+            let (queue, cancel) = (DownloadQueue::new(1), CancellationToken::new());
+            let events = Arc::new(RecordingEvents::default());
+            let job = download_job(
+                api,
+                games_dir.path().to_path_buf(),
+                game.clone(),
+                cancel.clone(),
+                events.clone(),
+            );
+            assert!(queue.enqueue(&game.date, cancel.clone(), job));
+
+            let part_path = paths::part_path(games_dir.path(), &game.date);
+            while std::fs::metadata(&part_path).map_or(0, |m| m.len()) == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            queue.pause();
+            wait_for_state(&queue, &game.date, DownloadState::Paused).await;
+            Self {
+                server,
+                queue,
+                games_dir,
+                game,
+                cancel,
+                events,
+                archive_len,
+            }
+        }
+
+        fn part_path(&self) -> PathBuf {
+            paths::part_path(self.games_dir.path(), &self.game.date)
+        }
+    }
+
+    /// Queue job downloading `game` into `games_dir` for real
+    fn download_job(
+        api: Arc<ApiClient>,
+        games_dir: PathBuf,
+        game: Game,
+        cancel: CancellationToken,
+        events: Arc<RecordingEvents>,
+    ) -> impl Fn(CancellationToken) -> BoxFuture<'static, Result<(), SdrError>> {
+        move |pause| {
+            let (api, games_dir, game) = (api.clone(), games_dir.clone(), game.clone());
+            let (cancel, events) = (cancel.clone(), events.clone());
+            Box::pin(async move {
+                download::download_game(
+                    &api,
+                    &games_dir,
+                    &game,
+                    &*events,
+                    &cancel,
+                    &pause,
+                    Duration::from_secs(download::DEFAULT_SPEED_WINDOW_SECS),
+                )
+                .await
+                .map(drop)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn a_paused_queue_keeps_the_part_file_and_resumes_from_it() {
+        let paused = PausedDownload::start().await;
+        let kept = std::fs::metadata(paused.part_path()).unwrap().len();
+        assert!(kept > 0 && kept < paused.archive_len, "kept {kept} bytes");
+
+        paused.queue.resume();
+        wait_until_settled(&paused.queue).await;
+
+        assert_eq!(paused.queue.status()[0].state, DownloadState::Done);
+        let requests = paused.server.requests();
+        assert_eq!(
+            requests.last().unwrap().header("Range"),
+            Some(format!("bytes={kept}-").as_str())
+        );
+        let installed = paths::game_dir(paused.games_dir.path(), &paused.game.date);
+        assert!(installed.join("game.bin").is_file());
+        assert!(!paused.part_path().exists());
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_paused_download_removes_its_part_file() {
+        let paused = PausedDownload::start().await;
+
+        paused.cancel.cancel();
+        wait_until_settled(&paused.queue).await;
+
+        let item = &paused.queue.status()[0];
+        assert_eq!(item.state, DownloadState::Failed);
+        assert_eq!(item.error.as_ref().unwrap().kind(), "Cancelled");
+        assert!(!paused.part_path().exists());
+        assert_eq!(
+            *paused.events.cancelled.lock().unwrap(),
+            [paused.game.date.as_str()]
+        );
+        assert_eq!(paused.server.hits(), 1);
+    }
+
+    #[tokio::test]
+    async fn cancelling_a_download_waiting_for_a_slot_frees_it_at_once() {
+        let queue = DownloadQueue::new(1);
+        let (blocker, cancel) = (CancellationToken::new(), CancellationToken::new());
+        let running = blocker.clone();
+        queue.enqueue("2024-01-01", CancellationToken::new(), move |_pause| {
+            let running = running.clone();
+            async move {
+                running.cancelled().await;
+                Ok(())
+            }
+        });
+        let waiting = cancel.clone();
+        queue.enqueue("2024-01-02", cancel.clone(), move |_pause| {
+            let waiting = waiting.clone();
+            async move {
+                assert!(waiting.is_cancelled());
+                Err(SdrError::Cancelled(String::from("cancelled")))
+            }
+        });
+        wait_for_state(&queue, "2024-01-01", DownloadState::Downloading).await;
+
+        cancel.cancel();
+        wait_for_state(&queue, "2024-01-02", DownloadState::Failed).await;
+
+        assert_eq!(queue.status()[0].state, DownloadState::Downloading);
+        blocker.cancel();
+        wait_until_settled(&queue).await;
+    }
+
+    #[tokio::test]
+    async fn runs_at_most_max_parallel_downloads() {
+        let server =
+            MockServer::start(|_| Response::new(200).delay(Duration::from_millis(100))).await;
+        let queue = DownloadQueue::new(2);
+        for index in 0..5 {
+            let url = format!("{}/games/{index}.zip", server.url);
+            let game_date = format!("2024-01-0{index}");
+            queue.enqueue(&game_date, CancellationToken::new(), move |_pause| {
+                let url = url.clone();
+                async move {
+                    if index == 1 {
+                        return Err(SdrError::Network(String::from("boom")));
+                    }
+                    reqwest::get(&url)
+                        .await
+                        .map(drop)
+                        .map_err(|e| SdrError::Network(e.to_string()))
+                }
+            });
+        }
+
+        let most_downloading = wait_until_settled(&queue).await;
+
+        assert_eq!(most_downloading, 2);
+        let states: Vec<DownloadState> = queue.status().iter().map(|item| item.state).collect();
+        assert_eq!(
+            states,
+            [
+                DownloadState::Done,
+                DownloadState::Failed,
+                DownloadState::Done,
+                DownloadState::Done,
+                DownloadState::Done,
+            ]
+        );
+        assert_eq!(server.hits(), 4);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_game_already_queued() {
+        let queue = DownloadQueue::new(1);
+        let job = |_pause| async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(())
+        };
+        let cancel = CancellationToken::new;
+        assert!(queue.enqueue("2024-01-01", cancel(), job));
+        assert!(!queue.enqueue("2024-01-01", cancel(), job));
+        wait_until_settled(&queue).await;
+        // Finished downloads can be queued again
+        assert!(queue.enqueue("2024-01-01", cancel(), job));
+    }
+
+    #[test]
+    fn a_batch_drops_repeats_and_skips_installed_games() {
+        let batch = Batch::default();
+        let dates = [
+            "2024-01-01",
+            "2024-01-02",
+            "2024-01-01",
+            "2024-01-03",
+            "2024-01-02",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        let selected = batch.select(dates, |game_date| game_date == "2024-01-02");
+
+        assert_eq!(selected, ["2024-01-01", "2024-01-03"]);
+        for game_date in &selected {
+            batch.add();
+            assert!(batch.finish(game_date, &Ok(())).is_none());
+        }
+        let summary = batch.seal().unwrap();
+        assert_eq!(summary.succeeded, selected);
+        assert_eq!(summary.skipped, ["2024-01-02"]);
+        assert!(summary.failed.is_empty());
+    }
+
+    #[test]
+    fn a_batch_summary_waits_for_the_last_download() {
+        let batch = Batch::default();
+        batch.add();
+        batch.add();
+        batch.skip_added("2024-01-02");
+        assert!(batch.seal().is_none());
+
+        let failure = Err(SdrError::Network(String::from("boom")));
+        let summary = batch.finish("2024-01-01", &failure).unwrap();
+        assert_eq!(summary.failed[0].game_date, "2024-01-01");
+        assert_eq!(summary.skipped, ["2024-01-02"]);
+    }
+}
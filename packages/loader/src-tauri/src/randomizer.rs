@@ -0,0 +1,341 @@
+// Picking a random game to play
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::SdrError;
+use crate::models::{Game, InstalledGame};
+use crate::paths;
+use crate::persist;
+use crate::stats::Stats;
+
+/// File name of the pick history inside the app config dir
+pub const HISTORY_FILE: &str = "history.json";
+
+/// How many recent picks are remembered
+pub const HISTORY_LEN: usize = 10;
+
+/// One past pick
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Date of the picked game, which is what installs and stats are keyed by
+    pub game_date: String,
+    pub picked_at: DateTime<Utc>,
+}
+
+/// Most recent picks, oldest first, as stored in `history.json`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Default location of the pick history
+    pub fn path() -> Result<PathBuf, SdrError> {
+        Ok(paths::config_dir()?.join(HISTORY_FILE))
+    }
+
+    /// Read the history at `path`, starting empty if it's missing or unreadable
+    pub fn load(path: &Path) -> Self {
+        persist::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SdrError> {
+        persist::save_json(path, self)
+    }
+
+    /// Apply `change` to the history at `path` and save them, one update at a time
+    pub fn update<R>(path: &Path, change: impl FnOnce(&mut Self) -> R) -> Result<R, SdrError> {
+        persist::update_json(path, change)
+    }
+
+    /// Remember a pick, forgetting the oldest once there are more than `HISTORY_LEN`
+    pub fn record(&mut self, game_date: &str) {
+        self.entries.push(HistoryEntry {
+            game_date: game_date.to_string(),
+            picked_at: Utc::now(),
+        });
+        let excess = self.entries.len().saturating_sub(HISTORY_LEN);
+        self.entries.drain(..excess);
+    }
+
+    pub fn contains(&self, game_date: &str) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.game_date == game_date)
+    }
+}
+
+/// `pool` without the games in `history`, or all of it if that would leave nothing
+pub fn exclude_recent(pool: Vec<Game>, history: &History) -> Vec<Game> {
+    let fresh: Vec<Game> = pool
+        .iter()
+        .filter(|game| !history.contains(&game.date))
+        .cloned()
+        .collect();
+    if fresh.is_empty() {
+        pool
+    } else {
+        fresh
+    }
+}
+
+/// Lowest weight a game can get under `WeightMode::LeastPlayed`
+///
+/// Keeps often-played games in the running so never-played ones don't win every time.
+const MIN_WEIGHT: f64 = 0.1;
+
+/// How candidates are weighted when picking
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeightMode {
+    /// Every game is equally likely
+    #[default]
+    Uniform,
+    /// Games appear less often the more times they have been played
+    LeastPlayed,
+}
+
+impl WeightMode {
+    fn weight(self, game: &Game, stats: &Stats) -> f64 {
+        match self {
+            WeightMode::Uniform => 1.0,
+            WeightMode::LeastPlayed => {
+                let plays = stats
+                    .games
+                    .get(&game.date)
+                    .map_or(0, |game| game.play_count);
+                (1.0 / (1.0 + f64::from(plays))).max(MIN_WEIGHT)
+            }
+        }
+    }
+}
+
+/// Pick one game from `pool`, weighted by `mode` against the play counts in `stats`
+///
+/// Play counts rather than the pick history drive `WeightMode::LeastPlayed`, as
+/// the history is short and recent picks are usually excluded from `pool`.
+/// With a `seed` the pick is reproducible for the same pool and stats, which is
+/// what makes shared or daily challenges possible; without one the RNG is seeded
+/// from entropy.
+pub fn pick_random_game(
+    pool: &[Game],
+    seed: Option<u64>,
+    mode: WeightMode,
+    stats: &Stats,
+) -> Result<Game, SdrError> {
+    if pool.is_empty() {
+        return Err(SdrError::NotFound(String::from(
+            "There are no games to pick from",
+        )));
+    }
+    // Order the pool so a seed gives the same pick however the list was fetched
+    let mut pool: Vec<&Game> = pool.iter().collect();
+    pool.sort_by(|a, b| a.date.cmp(&b.date));
+
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let weights = pool.iter().map(|game| mode.weight(game, stats));
+    let index = WeightedIndex::new(weights)
+        .map_err(|e| SdrError::Internal(format!("Invalid pick weights: {e}")))?;
+    Ok(pool[index.sample(&mut rng)].clone())
+}
+
+/// Today's game of the day, the same for everyone with the same catalog
+pub fn daily_game(catalog: &[Game]) -> Result<Game, SdrError> {
+    daily_game_for(catalog, Utc::now().date_naive())
+}
+
+/// Game of the day for `date` (UTC), picked from the full catalog
+///
+/// The seed comes from the date alone and personal history is ignored, so the
+/// pick only depends on `date` and the set of game dates in `catalog`. Adding or
+/// removing a game can change the pick for every date, past ones included.
+pub fn daily_game_for(catalog: &[Game], date: NaiveDate) -> Result<Game, SdrError> {
+    pick_random_game(
+        catalog,
+        Some(daily_seed(date)),
+        WeightMode::Uniform,
+        &Stats::default(),
+    )
+}
+
+/// First 8 bytes of the SHA-256 of `YYYY-MM-DD`
+fn daily_seed(date: NaiveDate) -> u64 {
+    let digest = Sha256::digest(date.format("%Y-%m-%d").to_string().as_bytes());
+    let mut seed = [0u8; 8];
+    seed.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(seed)
+}
+
+/// Installed games as `Game`s, filled in from the catalog where it knows them
+///
+/// Install folders are named after the game date, so that is what gets matched.
+pub fn installed_pool(installed: &[InstalledGame], catalog: &[Game]) -> Vec<Game> {
+    installed
+        .iter()
+        .map(|local| {
+            catalog
+                .iter()
+                .find(|game| game.date == local.id)
+                .cloned()
+                .unwrap_or_else(|| Game {
+                    id: local.id.clone(),
+                    date: local.id.clone(),
+                    title: local.title.clone(),
+                    size_bytes: local.size_on_disk,
+                    ..Game::default()
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::test_support::game;
+
+    fn catalog() -> Vec<Game> {
+        (1..=20)
+            .map(|day| game(&format!("g{day}"), &format!("2024-01-{day:02}")))
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_picks_the_same_game() {
+        let pool = catalog();
+        let mut shuffled = pool.clone();
+        shuffled.reverse();
+
+        let first =
+            pick_random_game(&pool, Some(42), WeightMode::Uniform, &Stats::default()).unwrap();
+        let again =
+            pick_random_game(&shuffled, Some(42), WeightMode::Uniform, &Stats::default()).unwrap();
+
+        assert_eq!(first, again);
+    }
+
+    #[test]
+    fn an_empty_pool_has_nothing_to_pick() {
+        let error =
+            pick_random_game(&[], Some(42), WeightMode::Uniform, &Stats::default()).unwrap_err();
+        assert_eq!(error.kind(), "NotFound");
+    }
+
+    #[test]
+    fn installed_games_are_matched_to_the_catalog_by_date() {
+        let catalog = vec![Game {
+            version: Some(String::from("2")),
+            ..game("tetris", "2024-01-01")
+        }];
+        let installed = [
+            InstalledGame {
+                id: String::from("2024-01-01"),
+                title: String::from("2024-01-01"),
+                install_path: PathBuf::from("/games/2024-01-01"),
+                size_on_disk: 10,
+                manifest: None,
+                launchable: true,
+            },
+            InstalledGame {
+                id: String::from("2023-12-31"),
+                title: String::from("Imported"),
+                install_path: PathBuf::from("/games/2023-12-31"),
+                size_on_disk: 20,
+                manifest: None,
+                launchable: true,
+            },
+        ];
+
+        let pool = installed_pool(&installed, &catalog);
+
+        assert_eq!(pool[0], catalog[0]);
+        assert_eq!(pool[1].date, "2023-12-31");
+        assert_eq!(pool[1].title, "Imported");
+    }
+
+    #[test]
+    fn a_just_picked_game_is_not_picked_again() {
+        let pool = catalog();
+        let mut history = History::default();
+        for seed in 0..50 {
+            let previous = history.entries.last().map(|entry| entry.game_date.clone());
+            let candidates = exclude_recent(pool.clone(), &history);
+            let picked = pick_random_game(
+                &candidates,
+                Some(seed),
+                WeightMode::Uniform,
+                &Stats::default(),
+            )
+            .unwrap();
+            assert_ne!(Some(&picked.date), previous.as_ref());
+            history.record(&picked.date);
+        }
+    }
+
+    #[test]
+    fn excluding_every_game_keeps_the_whole_pool() {
+        let pool = vec![game("a", "2024-01-01"), game("b", "2024-01-02")];
+        let mut history = History::default();
+        history.record("2024-01-01");
+        assert_eq!(exclude_recent(pool.clone(), &history), pool[1..]);
+
+        history.record("2024-01-02");
+        assert_eq!(exclude_recent(pool.clone(), &history), pool);
+    }
+
+    #[test]
+    fn least_played_favors_games_played_less() {
+        let pool = vec![
+            game("a", "2024-01-01"),
+            game("b", "2024-01-02"),
+            game("c", "2024-01-03"),
+        ];
+        let mut stats = Stats::default();
+        for _ in 0..9 {
+            stats.record_start("2024-01-01");
+        }
+        stats.record_start("2024-01-02");
+
+        let mut picks = BTreeMap::new();
+        for seed in 0..3000 {
+            let picked =
+                pick_random_game(&pool, Some(seed), WeightMode::LeastPlayed, &stats).unwrap();
+            *picks.entry(picked.date).or_insert(0) += 1;
+        }
+
+        // Weights are 0.1, 0.5 and 1, so about 6%, 31% and 63% of the picks
+        let share = |date: &str| f64::from(picks[date]) / 3000.0;
+        assert!((0.03..0.10).contains(&share("2024-01-01")), "{picks:?}");
+        assert!((0.25..0.37).contains(&share("2024-01-02")), "{picks:?}");
+        assert!((0.57..0.69).contains(&share("2024-01-03")), "{picks:?}");
+    }
+
+    #[test]
+    fn the_daily_game_only_depends_on_the_date() {
+        let pool = catalog();
+        let mut shuffled = pool.clone();
+        shuffled.reverse();
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 6, d).unwrap();
+
+        let today = daily_game_for(&pool, day(1)).unwrap();
+        assert_eq!(daily_game_for(&pool, day(1)).unwrap(), today);
+        assert_eq!(daily_game_for(&shuffled, day(1)).unwrap(), today);
+
+        // With 20 games, a month of the same pick would be a broken seed
+        let picks: std::collections::BTreeSet<_> = (1..=30)
+            .map(|d| daily_game_for(&pool, day(d)).unwrap().date)
+            .collect();
+        assert!(picks.len() > 1, "every day picked {picks:?}");
+    }
+}
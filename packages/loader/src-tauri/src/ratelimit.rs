@@ -0,0 +1,145 @@
+// Request rate cap shared by every call to the game server
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::error::SdrError;
+
+/// Requests per minute allowed when the config doesn't say otherwise
+pub const DEFAULT_REQUESTS_PER_MINUTE: u32 = 60;
+
+/// Longest a request waits for its turn before failing with `SdrError::RateLimited`
+pub const MAX_QUEUE_WAIT: Duration = Duration::from_secs(30);
+
+/// Token bucket limiting how often the loader may send a request
+///
+/// A sixth of the cap can go out at once, so a burst like opening the app
+/// isn't held up, and the bucket refills with the rest spread over the minute.
+/// That way no 60 second window ever sees more requests than the cap.
+pub struct RateLimiter {
+    refill_per_sec: f64,
+    capacity: f64,
+    max_wait: Duration,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    /// Requests that may go out right now; negative while requests are queued
+    available: f64,
+    /// On tokio's clock, the one `sleep` waits on
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32, max_wait: Duration) -> Self {
+        let per_minute = per_minute.max(1);
+        let burst = per_minute.div_ceil(6);
+        let capacity = f64::from(burst);
+        Self {
+            // At least one a minute, or a cap of one would never refill
+            refill_per_sec: f64::from((per_minute - burst).max(1)) / 60.0,
+            capacity,
+            max_wait,
+            bucket: Mutex::new(Bucket {
+                available: capacity,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until one more request is allowed
+    ///
+    /// Waiters are served in the order they arrived. Fails without using up a
+    /// turn if the wait would be longer than the limiter's `max_wait`.
+    pub async fn acquire(&self) -> Result<(), SdrError> {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.updated).as_secs_f64() * self.refill_per_sec;
+            let available = (bucket.available + refill).min(self.capacity) - 1.0;
+            let wait = Duration::from_secs_f64((-available).max(0.0) / self.refill_per_sec);
+            if wait > self.max_wait {
+                return Err(SdrError::RateLimited(format!(
+                    "Too many requests to the game server, the next one could go out in {}s",
+                    wait.as_secs()
+                )));
+            }
+            bucket.available = available;
+            bucket.updated = now;
+            wait
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tenths of a second each of `count` requests made at once waited for its turn
+    async fn waits(limiter: &RateLimiter, count: usize) -> Vec<Result<u64, String>> {
+        let started = tokio::time::Instant::now();
+        futures_util::future::join_all((0..count).map(|_| async {
+            limiter
+                .acquire()
+                .await
+                .map(|()| (started.elapsed().as_secs_f64() * 10.0).round() as u64)
+                .map_err(|e| e.kind().to_string())
+        }))
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_burst_goes_out_at_once_and_the_rest_is_paced() {
+        let limiter = RateLimiter::new(60, MAX_QUEUE_WAIT);
+
+        let waits = waits(&limiter, 13).await;
+
+        // Ten at once, then the other 50 of the minute one every 1.2 seconds
+        let mut expected = vec![Ok(0); 10];
+        expected.extend([Ok(12), Ok(24), Ok(36)]);
+        assert_eq!(waits, expected);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn requests_that_would_wait_too_long_are_rate_limited() {
+        let limiter = RateLimiter::new(60, Duration::from_secs(5));
+
+        let waits = waits(&limiter, 16).await;
+
+        assert_eq!(waits[13], Ok(48));
+        assert_eq!(waits[14], Err(String::from("RateLimited")));
+        // A refused request doesn't take a turn from the ones after it
+        assert_eq!(waits[15], Err(String::from("RateLimited")));
+        assert_eq!(limiter.acquire().await.map_err(|e| e.kind()), Ok(()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn no_minute_sees_more_requests_than_the_cap() {
+        for per_minute in [1, 7, 60, 600] {
+            let limiter = RateLimiter::new(per_minute, Duration::from_secs(600));
+
+            let granted: Vec<u64> = waits(&limiter, 3 * per_minute as usize)
+                .await
+                .into_iter()
+                .map(Result::unwrap)
+                .collect();
+
+            for &start in &granted {
+                let in_window = granted
+                    .iter()
+                    .filter(|&&at| at >= start && at < start + 600)
+                    .count();
+                assert!(
+                    in_window <= per_minute as usize,
+                    "{in_window} requests in the minute from {start} with a cap of {per_minute}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Searching and filtering the games list
+
+use chrono::NaiveDate;
+
+use crate::error::SdrError;
+use crate::models::Game;
+
+/// Format of game dates and of the date range bounds
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Games whose title fuzzily matches `query`, best matches first
+///
+/// An empty query returns every game in its original order.
+pub fn search_games(games: &[Game], query: &str) -> Vec<Game> {
+    let query = query.trim();
+    if query.is_empty() {
+        return games.to_vec();
+    }
+    let mut matches: Vec<(i64, &Game)> = games
+        .iter()
+        .filter_map(|game| Some((match_score(&game.title, query)?, game)))
+        .collect();
+    // Stable, so games that score the same keep the list's order
+    matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    matches.into_iter().map(|(_, game)| game.clone()).collect()
+}
+
+/// Score `query` as a case-insensitive subsequence of `title`, or `None` if it isn't one
+///
+/// Runs of consecutive characters and matches at the start of a word score
+/// higher, gaps score lower, and a title starting with the query beats them all.
+pub fn match_score(title: &str, query: &str) -> Option<i64> {
+    let title: Vec<char> = title.to_lowercase().chars().collect();
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    let mut position = 0;
+    for &wanted in &query {
+        let index = position + title[position..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        } else if let Some(previous) = previous {
+            score -= (index - previous - 1).min(5) as i64;
+        }
+        if index == 0 || !title[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(index);
+        position = index + 1;
+    }
+    if title.starts_with(&query) {
+        score += 100;
+    }
+    Some(score)
+}
+
+/// Games dated between `from` and `to`, both inclusive and either optional
+///
+/// Games whose date can't be parsed are left out.
+pub fn filter_games(
+    games: &[Game],
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Vec<Game>, SdrError> {
+    let from = from.map(parse_date).transpose()?;
+    let to = to.map(parse_date).transpose()?;
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(SdrError::Invalid(format!(
+                "The range starts ({from}) after it ends ({to})"
+            )));
+        }
+    }
+    Ok(games
+        .iter()
+        .filter(|game| {
+            NaiveDate::parse_from_str(&game.date, DATE_FORMAT).is_ok_and(|date| {
+                from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
+            })
+        })
+        .cloned()
+        .collect())
+}
+
+/// Parse a `YYYY-MM-DD` date given by the caller
+pub fn parse_date(date: &str) -> Result<NaiveDate, SdrError> {
+    NaiveDate::parse_from_str(date.trim(), DATE_FORMAT)
+        .map_err(|e| SdrError::Invalid(format!("{date:?} is not a date like 2024-01-31: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::game;
+
+    fn titled(title: &str, date: &str) -> Game {
+        Game {
+            title: title.to_string(),
+            ..game(title, date)
+        }
+    }
+
+    #[test]
+    fn a_subsequence_matches_across_gaps() {
+        assert!(match_score("Zelda", "zld").is_some());
+        assert!(match_score("The Legend of Zelda", "ZLD").is_some());
+        assert_eq!(match_score("Zelda", "zdl"), None);
+        assert_eq!(match_score("Zelda", "zeldas"), None);
+    }
+
+    #[test]
+    fn consecutive_and_word_start_matches_score_higher() {
+        let tight = match_score("Space Zelda", "zel").unwrap();
+        let gappy = match_score("Space Zombie Elf", "zel").unwrap();
+        let mid_word = match_score("Bazelda", "zel").unwrap();
+        assert!(tight > gappy, "{tight} vs {gappy}");
+        assert!(tight > mid_word, "{tight} vs {mid_word}");
+    }
+
+    #[test]
+    fn titles_starting_with_the_query_rank_first() {
+        let games = [
+            titled("The Legend of Zelda", "2024-01-01"),
+            titled("Zelda II", "2024-01-02"),
+            titled("Tetris", "2024-01-03"),
+            titled("Zelda Maker", "2024-01-04"),
+        ];
+
+        let titles: Vec<String> = search_games(&games, " zel ")
+            .into_iter()
+            .map(|game| game.title)
+            .collect();
+
+        assert_eq!(titles, ["Zelda II", "Zelda Maker", "The Legend of Zelda"]);
+        assert_eq!(search_games(&games, "").len(), 4);
+    }
+
+    #[test]
+    fn date_bounds_are_inclusive() {
+        let games = [
+            game("a", "2024-01-01"),
+            game("b", "2024-01-15"),
+            game("c", "2024-01-31"),
+            game("d", "2024-02-01"),
+            game("e", "someday"),
+        ];
+        let ids = |from, to| -> Vec<String> {
+            filter_games(&games, from, to)
+                .unwrap()
+                .into_iter()
+                .map(|game| game.id)
+                .collect()
+        };
+
+        assert_eq!(ids(Some("2024-01-01"), Some("2024-01-31")), ["a", "b", "c"]);
+        assert_eq!(ids(Some("2024-01-31"), None), ["c", "d"]);
+        assert_eq!(ids(None, Some("2024-01-01")), ["a"]);
+        assert_eq!(ids(Some("2024-01-15"), Some("2024-01-15")), ["b"]);
+        // Without bounds only the unparseable date is left out
+        assert_eq!(ids(None, None), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn invalid_ranges_are_rejected() {
+        let error = filter_games(&[], Some("2024-02-01"), Some("2024-01-01")).unwrap_err();
+        assert_eq!(error.kind(), "Invalid");
+        let error = filter_games(&[], Some("01/02/2024"), None).unwrap_err();
+        assert_eq!(error.kind(), "Invalid");
+    }
+}
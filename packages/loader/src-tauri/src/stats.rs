@@ -0,0 +1,168 @@
+// Play counts and play time per game
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::SdrError;
+use crate::paths;
+use crate::persist;
+
+/// File name of the play statistics inside the app config dir
+pub const STATS_FILE: &str = "stats.json";
+
+/// Aggregated plays of a single game
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GameStats {
+    pub game_date: String,
+    pub play_count: u32,
+    pub total_play_secs: u64,
+    pub last_played: Option<DateTime<Utc>>,
+}
+
+/// Contents of `stats.json`, keyed by game date
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub games: BTreeMap<String, GameStats>,
+}
+
+impl Stats {
+    /// Count a new play of `game_date`, its duration added once known
+    pub fn record_start(&mut self, game_date: &str) {
+        let entry = self.entry(game_date);
+        entry.play_count += 1;
+        entry.last_played = Some(Utc::now());
+    }
+
+    /// Add `duration_secs` of play time to `game_date`
+    pub fn record_time(&mut self, game_date: &str, duration_secs: u64) {
+        let entry = self.entry(game_date);
+        entry.total_play_secs = entry.total_play_secs.saturating_add(duration_secs);
+    }
+
+    /// Every game's stats, most played first, then by play time
+    pub fn sorted(&self) -> Vec<GameStats> {
+        let mut games: Vec<GameStats> = self.games.values().cloned().collect();
+        games.sort_by(|a, b| {
+            b.play_count
+                .cmp(&a.play_count)
+                .then(b.total_play_secs.cmp(&a.total_play_secs))
+                .then(a.game_date.cmp(&b.game_date))
+        });
+        games
+    }
+
+    fn entry(&mut self, game_date: &str) -> &mut GameStats {
+        self.games
+            .entry(game_date.to_string())
+            .or_insert_with(|| GameStats {
+                game_date: game_date.to_string(),
+                ..GameStats::default()
+            })
+    }
+}
+
+/// The loaded stats and where they are saved, held in Tauri managed state
+pub struct StatsStore {
+    path: PathBuf,
+    stats: Mutex<Stats>,
+}
+
+impl StatsStore {
+    /// Load `stats.json` from the app config dir
+    pub fn open() -> Result<Self, SdrError> {
+        Ok(Self::load(paths::config_dir()?.join(STATS_FILE)))
+    }
+
+    /// Load the stats at `path`, starting empty if they're missing or invalid
+    pub fn load(path: PathBuf) -> Self {
+        Self {
+            stats: Mutex::new(persist::load_json(&path)),
+            path,
+        }
+    }
+
+    /// Snapshot of the current stats
+    pub fn get(&self) -> Stats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Apply `change` and write the result back to disk
+    pub fn update(&self, change: impl FnOnce(&mut Stats)) -> Result<Stats, SdrError> {
+        let mut stats = self.stats.lock().unwrap();
+        let mut updated = stats.clone();
+        change(&mut updated);
+        persist::save_json(&self.path, &updated)?;
+        *stats = updated.clone();
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_and_play_time_add_up_per_game() {
+        let mut stats = Stats::default();
+        stats.record_start("2024-01-01");
+        stats.record_time("2024-01-01", 60);
+        stats.record_start("2024-01-01");
+        stats.record_time("2024-01-01", 30);
+        stats.record_start("2024-01-02");
+
+        let game = &stats.games["2024-01-01"];
+        assert_eq!(game.game_date, "2024-01-01");
+        assert_eq!(game.play_count, 2);
+        assert_eq!(game.total_play_secs, 90);
+        assert!(game.last_played.is_some());
+        assert_eq!(stats.games["2024-01-02"].total_play_secs, 0);
+    }
+
+    #[test]
+    fn sorted_puts_the_most_played_first() {
+        let mut stats = Stats::default();
+        for (game_date, plays, secs) in [
+            ("2024-01-01", 1, 500),
+            ("2024-01-02", 3, 10),
+            ("2024-01-03", 1, 900),
+            ("2024-01-04", 1, 500),
+        ] {
+            for _ in 0..plays {
+                stats.record_start(game_date);
+            }
+            stats.record_time(game_date, secs);
+        }
+
+        let order: Vec<String> = stats
+            .sorted()
+            .into_iter()
+            .map(|game| game.game_date)
+            .collect();
+
+        // Play count, then play time, then date breaks the tie
+        assert_eq!(
+            order,
+            ["2024-01-02", "2024-01-03", "2024-01-01", "2024-01-04"]
+        );
+    }
+
+    #[test]
+    fn updates_are_saved_and_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(STATS_FILE);
+        let store = StatsStore::load(path.clone());
+
+        store
+            .update(|stats| stats.record_start("2024-01-01"))
+            .unwrap();
+
+        let reloaded = StatsStore::load(path).get();
+        assert_eq!(reloaded.games["2024-01-01"].play_count, 1);
+    }
+}
@@ -0,0 +1,173 @@
+// Background refresh of the games list
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tokio::sync::watch;
+
+use crate::api::ApiClient;
+use crate::catalog::{self, GamesList};
+use crate::error::SdrError;
+use crate::models::Game;
+
+/// Minutes between two syncs unless the config says otherwise
+pub const DEFAULT_SYNC_INTERVAL_MINS: u64 = 30;
+
+/// Longest wait between two syncs while the server keeps failing
+const MAX_SYNC_BACKOFF: Duration = Duration::from_secs(4 * 60 * 60);
+
+/// Whether the background sync runs, held in Tauri managed state
+pub struct AutoSync {
+    enabled: watch::Sender<bool>,
+}
+
+impl AutoSync {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: watch::Sender::new(enabled),
+        }
+    }
+
+    /// Pause or resume the sync; resuming waits a full interval before the next fetch
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.send_replace(enabled);
+    }
+}
+
+impl Default for AutoSync {
+    fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+/// Spawn the task that refreshes the games list every `interval`
+///
+/// Emits `games-updated` with the new list whenever the set of games or their
+/// checksums changed. Failed syncs double the wait, up to `MAX_SYNC_BACKOFF`.
+pub fn spawn<R: Runtime>(app: AppHandle<R>, interval: Duration) {
+    let mut enabled = app.state::<AutoSync>().enabled.subscribe();
+    tauri::async_runtime::spawn(async move {
+        let mut delay = interval;
+        loop {
+            while !*enabled.borrow_and_update() {
+                if enabled.changed().await.is_err() {
+                    return;
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                // Paused or resumed while waiting, start the wait over
+                _ = enabled.changed() => continue,
+            }
+
+            let api = app.state::<ApiClient>();
+            let synced = async { sync_once(&api, &catalog::cache_path()?).await }.await;
+            match synced {
+                Ok(Some(list)) => {
+                    delay = interval;
+                    let _ = app.emit("games-updated", list);
+                }
+                Ok(None) => delay = interval,
+                Err(e) => {
+                    delay = delay.saturating_mul(2).min(MAX_SYNC_BACKOFF.max(interval));
+                    tracing::warn!(
+                        "games list sync failed, retrying in {} min: {e}",
+                        delay.as_secs() / 60
+                    );
+                }
+            }
+        }
+    });
+}
+
+/// Refresh the list cached at `cache_path`, returning it if it changed
+async fn sync_once(api: &ApiClient, cache_path: &Path) -> Result<Option<GamesList>, SdrError> {
+    let before = catalog::load_cache(cache_path).map(|cache| fingerprint(&cache.games));
+    let list = catalog::refresh(api, cache_path).await?;
+    if list.from_cache {
+        return Err(SdrError::Network(String::from(
+            "Game server unreachable, kept the cached list",
+        )));
+    }
+    if before.as_ref() == Some(&fingerprint(&list.games)) {
+        Ok(None)
+    } else {
+        Ok(Some(list))
+    }
+}
+
+/// What counts as a change to the list: a game added, removed or repackaged
+fn fingerprint(games: &[Game]) -> BTreeSet<(String, Option<String>)> {
+    games
+        .iter()
+        .map(|game| (game.id.clone(), game.checksum.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::api::RetryPolicy;
+    use crate::config::ServerConfig;
+    use crate::test_support::{game, games_body, MockServer, Response};
+
+    #[tokio::test]
+    async fn sync_reports_only_real_changes() {
+        let served = Arc::new(Mutex::new(Some(vec![
+            game("a", "2024-01-01"),
+            game("b", "2024-01-02"),
+        ])));
+        let server = MockServer::start({
+            let served = served.clone();
+            move |_| match &*served.lock().unwrap() {
+                Some(games) => Response::json(games_body(games)),
+                None => Response::new(503),
+            }
+        })
+        .await;
+        let api = ApiClient::new(ServerConfig::new([&server.url])).with_retry(RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join(catalog::CACHE_FILE);
+        let set_served = |games: Option<Vec<Game>>| *served.lock().unwrap() = games;
+
+        // Nothing cached yet, so the first list counts as a change
+        assert!(sync_once(&api, &cache_path).await.unwrap().is_some());
+        assert!(sync_once(&api, &cache_path).await.unwrap().is_none());
+
+        // Same games in another order, or a changed title, aren't a change
+        set_served(Some(vec![
+            Game {
+                title: String::from("Renamed"),
+                ..game("b", "2024-01-02")
+            },
+            game("a", "2024-01-01"),
+        ]));
+        assert!(sync_once(&api, &cache_path).await.unwrap().is_none());
+
+        set_served(Some(vec![
+            game("a", "2024-01-01"),
+            Game {
+                checksum: Some(String::from("new build")),
+                ..game("b", "2024-01-02")
+            },
+        ]));
+        let list = sync_once(&api, &cache_path).await.unwrap().unwrap();
+        assert_eq!(list.games.len(), 2);
+
+        set_served(Some(vec![game("a", "2024-01-01")]));
+        assert!(sync_once(&api, &cache_path).await.unwrap().is_some());
+
+        set_served(None);
+        assert_eq!(
+            sync_once(&api, &cache_path).await.unwrap_err().kind(),
+            "Network"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+// Helpers shared by the unit tests: a scripted HTTP server and sample data
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rand::RngCore;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::pki_types::PrivateKeyDer;
+use tokio_rustls::{rustls, TlsAcceptor};
+
+use crate::download::{DownloadEvents, DownloadProgress};
+use crate::extract::ExtractProgress;
+use crate::models::Game;
+
+/// A request received by `MockServer`
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// Request target as sent, a path or an absolute URL when sent to a proxy
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Target without the query string
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+}
+
+/// What `MockServer` answers with
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Wait before sending anything
+    pub delay: Duration,
+    /// Send the body in pieces of this size, waiting `chunk_delay` after each
+    pub chunk_size: Option<usize>,
+    pub chunk_delay: Duration,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+            delay: Duration::ZERO,
+            chunk_size: None,
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    pub fn json(body: impl Into<String>) -> Self {
+        Self::new(200)
+            .header("Content-Type", "application/json")
+            .body(body.into().into_bytes())
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Send the body `chunk_size` bytes at a time, waiting `chunk_delay` after each
+    pub fn slow(mut self, chunk_size: usize, chunk_delay: Duration) -> Self {
+        self.chunk_size = Some(chunk_size);
+        self.chunk_delay = chunk_delay;
+        self
+    }
+}
+
+type Handler = dyn Fn(&Request) -> Response + Send + Sync;
+
+/// HTTP/1.1 server on localhost answering every request with `handler`
+///
+/// Each connection serves a single request and is then closed, which keeps
+/// the parsing trivial. Stops when dropped.
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start(handler: impl Fn(&Request) -> Response + Send + Sync + 'static) -> Self {
+        Self::spawn(Arc::new(handler), None).await
+    }
+
+    /// Like `start`, but over HTTPS with a fresh self-signed certificate
+    ///
+    /// Also returns the certificate, DER encoded.
+    pub async fn start_tls(
+        handler: impl Fn(&Request) -> Response + Send + Sync + 'static,
+    ) -> (Self, Vec<u8>) {
+        let (acceptor, certificate) = self_signed_tls();
+        let server = Self::spawn(Arc::new(handler), Some(acceptor)).await;
+        (server, certificate)
+    }
+
+    async fn spawn(handler: Arc<Handler>, tls: Option<TlsAcceptor>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        let url = format!("{scheme}://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (handler, log, tls) = (handler.clone(), log.clone(), tls.clone());
+                tokio::spawn(async move {
+                    let _ = match tls {
+                        Some(tls) => match tls.accept(stream).await {
+                            Ok(stream) => serve(stream, &*handler, &log).await,
+                            Err(e) => Err(e),
+                        },
+                        None => serve(stream, &*handler, &log).await,
+                    };
+                });
+            }
+        });
+        Self {
+            url,
+            requests,
+            task,
+        }
+    }
+
+    /// Every request received so far, oldest first
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn hits(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Answer the single request on `stream`
+pub async fn serve<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    handler: &Handler,
+    log: &Mutex<Vec<Request>>,
+) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte).await? == 0 {
+            return Ok(());
+        }
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.split("\r\n");
+    let mut start = lines.next().unwrap_or_default().split(' ');
+    let request = Request {
+        method: start.next().unwrap_or_default().to_string(),
+        target: start.next().unwrap_or_default().to_string(),
+        headers: lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect(),
+    };
+    if let Some(length) = request
+        .header("Content-Length")
+        .and_then(|length| length.parse::<usize>().ok())
+    {
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await?;
+    }
+    log.lock().unwrap().push(request.clone());
+
+    let response = handler(&request);
+    tokio::time::sleep(response.delay).await;
+    let mut head = format!("HTTP/1.1 {} Mock\r\n", response.status);
+    for (key, value) in &response.headers {
+        head.push_str(&format!("{key}: {value}\r\n"));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        response.body.len()
+    ));
+    stream.write_all(head.as_bytes()).await?;
+    if request.method != "HEAD" {
+        match response.chunk_size {
+            Some(size) => {
+                for chunk in response.body.chunks(size) {
+                    stream.write_all(chunk).await?;
+                    stream.flush().await?;
+                    tokio::time::sleep(response.chunk_delay).await;
+                }
+            }
+            None => stream.write_all(&response.body).await?,
+        }
+    }
+    stream.flush().await?;
+    stream.shutdown().await
+}
+
+/// TLS acceptor with a fresh self-signed certificate, and that certificate as DER
+pub fn self_signed_tls() -> (TlsAcceptor, Vec<u8>) {
+    let certified = rcgen::generate_simple_self_signed([String::from("localhost")]).unwrap();
+    let certificate = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(certified.key_pair.serialize_der().into());
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate.clone()], key)
+        .unwrap();
+    (TlsAcceptor::from(Arc::new(config)), certificate.to_vec())
+}
+
+/// URL of a local port nothing listens on
+pub async fn closed_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    url
+}
+
+/// A game with just the fields most tests care about
+pub fn game(id: &str, date: &str) -> Game {
+    Game {
+        id: id.to_string(),
+        date: date.to_string(),
+        title: format!("Game {id}"),
+        ..Game::default()
+    }
+}
+
+/// `{ "games": [...] }` body for `games`
+pub fn games_body(games: &[Game]) -> String {
+    serde_json::json!({ "games": games }).to_string()
+}
+
+/// Zip archive holding `files`, as `(path, contents)` pairs
+pub fn zip_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (path, contents) in files {
+        zip.start_file(*path, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        std::io::Write::write_all(&mut zip, contents).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+/// Archive of a game holding 1 MiB of incompressible data
+pub fn sample_archive() -> Vec<u8> {
+    let mut data = vec![0u8; 1024 * 1024];
+    rand::thread_rng().fill_bytes(&mut data);
+    zip_archive(&[("game.bin", &data), ("manifest.json", b"{}")])
+}
+
+/// `archive`, or the part of it asked for by `Range: bytes=<start>-`
+pub fn archive_response(archive: &[u8], request: &Request) -> Response {
+    let start = request
+        .header("Range")
+        .and_then(|range| range.strip_prefix("bytes="))
+        .and_then(|range| range.strip_suffix('-'))
+        .and_then(|start| start.parse::<usize>().ok());
+    match start {
+        Some(start) => Response::new(206)
+            .header(
+                "Content-Range",
+                &format!("bytes {start}-{}/{}", archive.len() - 1, archive.len()),
+            )
+            .body(archive[start..].to_vec()),
+        None => Response::new(200).body(archive.to_vec()),
+    }
+}
+
+/// `DownloadEvents` that remembers everything it was told
+#[derive(Default)]
+pub struct RecordingEvents {
+    pub progress: Mutex<Vec<DownloadProgress>>,
+    pub completed: Mutex<Vec<String>>,
+    pub errors: Mutex<Vec<String>>,
+    pub cancelled: Mutex<Vec<String>>,
+}
+
+impl DownloadEvents for RecordingEvents {
+    fn progress(&self, progress: &DownloadProgress) {
+        self.progress.lock().unwrap().push(progress.clone());
+    }
+
+    fn complete(&self, game_date: &str, _path: &std::path::Path) {
+        self.completed.lock().unwrap().push(game_date.to_string());
+    }
+
+    fn error(&self, _game_date: &str, message: &str) {
+        self.errors.lock().unwrap().push(message.to_string());
+    }
+
+    fn cancelled(&self, game_date: &str) {
+        self.cancelled.lock().unwrap().push(game_date.to_string());
+    }
+
+    fn extract_progress(&self, _progress: &ExtractProgress) {}
+}
@@ -0,0 +1,163 @@
+// Local cache of game cover art
+
+use std::path::{Path, PathBuf};
+
+use crate::api::{self, ApiClient};
+use crate::error::SdrError;
+use crate::models::Game;
+use crate::paths;
+
+/// Local copy of `game`'s thumbnail, downloading it on first use
+///
+/// Returns `None` when the game has no thumbnail or it couldn't be fetched, so
+/// a missing image never fails the caller. Cached files are named after the game id.
+pub async fn get_thumbnail(
+    api: &ApiClient,
+    dir: &Path,
+    game: &Game,
+) -> Result<Option<PathBuf>, SdrError> {
+    let Some(url) = game
+        .thumbnail_url
+        .as_deref()
+        .filter(|url| !url.trim().is_empty())
+    else {
+        return Ok(None);
+    };
+    paths::check_game_date(&game.id)?;
+    let path = dir.join(format!("{}.{}", game.id, extension(url)));
+    if tokio::fs::metadata(&path)
+        .await
+        .is_ok_and(|m| m.is_file() && m.len() > 0)
+    {
+        return Ok(Some(path));
+    }
+
+    let bytes = match fetch(api, url).await {
+        Ok(bytes) if !bytes.is_empty() => bytes,
+        Ok(_) => {
+            tracing::warn!("thumbnail of {} is empty", game.id);
+            return Ok(None);
+        }
+        Err(e) => {
+            tracing::warn!("couldn't fetch thumbnail of {}: {e}", game.id);
+            return Ok(None);
+        }
+    };
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| SdrError::Io(format!("Failed to create {}: {e}", dir.display())))?;
+    // Written under a temporary name so a half-written file is never served
+    let temp = dir.join(format!(".{}.download", game.id));
+    tokio::fs::write(&temp, &bytes)
+        .await
+        .map_err(|e| SdrError::Io(format!("Failed to write {}: {e}", temp.display())))?;
+    tokio::fs::rename(&temp, &path)
+        .await
+        .map_err(|e| SdrError::Io(format!("Failed to move {} into place: {e}", temp.display())))?;
+    Ok(Some(path))
+}
+
+/// GET a thumbnail, resolving paths relative to the game server
+///
+/// Absolute URLs are requested as they are, without trying every mirror.
+async fn fetch(api: &ApiClient, url: &str) -> Result<Vec<u8>, SdrError> {
+    let response = if url.starts_with("http://") || url.starts_with("https://") {
+        api.get_url_raw(url).await?
+    } else {
+        api.get_raw(
+            |base_url| format!("{base_url}/{}", url.trim_start_matches('/')),
+            |request| request,
+        )
+        .await?
+    };
+    let url = response.url().to_string();
+    let response = api::check_status(response, &url)?;
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| api::network_error(&e, format!("Failed to read {url}: {e}")))?;
+    Ok(bytes.to_vec())
+}
+
+/// File extension of the image at `url`, `img` if it doesn't have a sensible one
+fn extension(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    path.rsplit_once('/')
+        .map_or(path, |(_, name)| name)
+        .rsplit_once('.')
+        .map(|(_, extension)| extension)
+        .filter(|extension| {
+            !extension.is_empty()
+                && extension.len() <= 5
+                && extension.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+        .unwrap_or("img")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ServerConfig;
+    use crate::test_support::{game, MockServer, Response};
+
+    fn with_thumbnail(url: &str) -> Game {
+        Game {
+            thumbnail_url: Some(url.to_string()),
+            ..game("g1", "2024-01-01")
+        }
+    }
+
+    #[tokio::test]
+    async fn a_thumbnail_is_downloaded_once_then_served_from_the_cache() {
+        let server = MockServer::start(|_| Response::new(200).body(b"png bytes".to_vec())).await;
+        let api = ApiClient::new(ServerConfig::new([&server.url]));
+        let dir = tempfile::tempdir().unwrap();
+        let game = with_thumbnail("/thumbnails/g1.png?v=2");
+
+        let first = get_thumbnail(&api, dir.path(), &game)
+            .await
+            .unwrap()
+            .unwrap();
+        let second = get_thumbnail(&api, dir.path(), &game)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(first, dir.path().join("g1.png"));
+        assert_eq!(second, first);
+        assert_eq!(std::fs::read(&first).unwrap(), b"png bytes");
+        assert_eq!(server.hits(), 1);
+        assert_eq!(server.requests()[0].target, "/thumbnails/g1.png?v=2");
+    }
+
+    #[tokio::test]
+    async fn the_api_key_stays_off_other_hosts() {
+        let mirror = MockServer::start(|_| Response::new(200).body(b"png".to_vec())).await;
+        let cdn = MockServer::start(|_| Response::new(200).body(b"png".to_vec())).await;
+        let api = ApiClient::new(ServerConfig::new([&mirror.url, &mirror.url]))
+            .with_api_key(Some(String::from("secret")));
+        let dir = tempfile::tempdir().unwrap();
+
+        let on_cdn = with_thumbnail(&format!("{}/g1.png", cdn.url));
+        get_thumbnail(&api, dir.path(), &on_cdn)
+            .await
+            .unwrap()
+            .unwrap();
+        let on_mirror = Game {
+            id: String::from("g2"),
+            ..with_thumbnail(&format!("{}/g2.png", mirror.url))
+        };
+        get_thumbnail(&api, dir.path(), &on_mirror)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(cdn.hits(), 1);
+        assert_eq!(cdn.requests()[0].header("Authorization"), None);
+        assert_eq!(mirror.hits(), 1);
+        assert_eq!(
+            mirror.requests()[0].header("Authorization"),
+            Some("Bearer secret")
+        );
+    }
+}
@@ -0,0 +1,148 @@
+// Certificate pinning for connections to the game server
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{self, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, OtherError, SignatureScheme};
+use sha2::{Digest, Sha256};
+
+/// Lowercase hex without separators, `None` if `pin` is unset or holds no hex digits
+///
+/// Lets pins be pasted in any common format, with or without colons.
+pub fn normalize_pin(pin: Option<&str>) -> Option<String> {
+    let pin: String = pin?
+        .chars()
+        .filter(char::is_ascii_hexdigit)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    (!pin.is_empty()).then_some(pin)
+}
+
+/// A server presented a certificate other than the pinned one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinMismatch {
+    pub actual: String,
+    pub expected: String,
+}
+
+impl fmt::Display for PinMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "certificate has fingerprint {}, expected {}",
+            self.actual, self.expected
+        )
+    }
+}
+
+impl Error for PinMismatch {}
+
+/// Accepts exactly the certificate whose SHA-256 (of the DER) is `pin`
+///
+/// Takes the place of the CA and host name checks, so a pinned certificate may
+/// be self-signed. Handshake signatures are still verified, so only a server
+/// holding the certificate's private key gets through.
+#[derive(Debug)]
+struct PinnedVerifier {
+    pin: String,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let actual = format!("{:x}", Sha256::digest(end_entity.as_ref()));
+        if actual == self.pin {
+            return Ok(ServerCertVerified::assertion());
+        }
+        let mismatch = PinMismatch {
+            actual,
+            expected: self.pin.clone(),
+        };
+        Err(rustls::Error::InvalidCertificate(CertificateError::Other(
+            OtherError(Arc::new(mismatch)),
+        )))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// TLS settings trusting only the certificate with fingerprint `pin`
+///
+/// `pin` must already be normalized by `normalize_pin`. The check runs during
+/// the handshake, so nothing is sent to a server that fails it.
+pub fn pinned_config(pin: &str) -> rustls::ClientConfig {
+    let provider = Arc::new(crypto::ring::default_provider());
+    let verifier = PinnedVerifier {
+        pin: pin.to_string(),
+        provider: provider.clone(),
+    };
+    rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("the ring provider supports the default TLS versions")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth()
+}
+
+/// The pin check that made a connection fail, if that is what happened
+///
+/// Walks the chain of causes. TLS errors travel inside `io::Error`s, whose own
+/// `source` skips the error they wrap, so those are unwrapped by hand.
+pub fn find_pin_mismatch<'a>(error: &'a (dyn Error + 'static)) -> Option<&'a PinMismatch> {
+    let mut current = Some(error);
+    while let Some(error) = current {
+        if let Some(rustls::Error::InvalidCertificate(CertificateError::Other(other))) =
+            error.downcast_ref::<rustls::Error>()
+        {
+            return other.0.downcast_ref::<PinMismatch>();
+        }
+        current = match error.downcast_ref::<std::io::Error>() {
+            Some(io) => io.get_ref().map(|inner| inner as &(dyn Error + 'static)),
+            None => error.source(),
+        };
+    }
+    None
+}
@@ -0,0 +1,95 @@
+// Finding installed games that have a newer build on the server
+
+use crate::models::{Game, InstalledGame, UpdateInfo};
+
+/// Installed games whose recorded checksum or version differs from the catalog's
+///
+/// Games the catalog doesn't list, or that were installed before the loader
+/// recorded builds, can't be compared and are left out.
+pub fn check_updates(installed: &[InstalledGame], catalog: &[Game]) -> Vec<UpdateInfo> {
+    installed
+        .iter()
+        .filter_map(|local| {
+            let latest = catalog.iter().find(|game| game.date == local.id)?;
+            let manifest = local.manifest.as_ref()?;
+            let checksum_changed =
+                differs(manifest.checksum.as_deref(), latest.checksum.as_deref());
+            let version_changed = differs(manifest.version.as_deref(), latest.version.as_deref());
+            (checksum_changed || version_changed).then(|| UpdateInfo {
+                id: local.id.clone(),
+                title: local.title.clone(),
+                installed_checksum: manifest.checksum.clone(),
+                installed_version: manifest.version.clone(),
+                latest_checksum: latest.checksum.clone(),
+                latest_version: latest.version.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Whether two known values disagree; an unknown value on either side never counts
+fn differs(installed: Option<&str>, latest: Option<&str>) -> bool {
+    match (installed, latest) {
+        (Some(installed), Some(latest)) => !installed.trim().eq_ignore_ascii_case(latest.trim()),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::models::Manifest;
+    use crate::test_support::game;
+
+    fn installed(game_date: &str, checksum: Option<&str>) -> InstalledGame {
+        InstalledGame {
+            id: game_date.to_string(),
+            title: format!("Installed {game_date}"),
+            install_path: PathBuf::from("/games").join(game_date),
+            size_on_disk: 1,
+            manifest: Some(Manifest {
+                checksum: checksum.map(String::from),
+                ..Manifest::default()
+            }),
+            launchable: true,
+        }
+    }
+
+    fn served(game_date: &str, checksum: Option<&str>) -> Game {
+        Game {
+            checksum: checksum.map(String::from),
+            ..game(game_date, game_date)
+        }
+    }
+
+    #[test]
+    fn a_differing_checksum_is_an_update() {
+        let local = [
+            installed("2024-01-01", Some("aaa")),
+            installed("2024-01-02", Some("BBB")),
+            installed("2024-01-03", None),
+            installed("2024-01-04", Some("ddd")),
+        ];
+        let catalog = [
+            served("2024-01-01", Some("fff")),
+            served("2024-01-02", Some("bbb\n")),
+            served("2024-01-03", Some("ccc")),
+        ];
+
+        let updates = check_updates(&local, &catalog);
+
+        assert_eq!(
+            updates,
+            [UpdateInfo {
+                id: String::from("2024-01-01"),
+                title: String::from("Installed 2024-01-01"),
+                installed_checksum: Some(String::from("aaa")),
+                installed_version: None,
+                latest_checksum: Some(String::from("fff")),
+                latest_version: None,
+            }]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+// Checking installed games against the file hashes recorded at install
+
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::checksum;
+use crate::error::SdrError;
+use crate::install;
+use crate::models::InstalledGame;
+
+/// Games hashed at the same time, kept low so the SD card isn't thrashed
+pub const VERIFY_PARALLEL: usize = 2;
+
+/// Outcome of verifying one installed game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum VerifyStatus {
+    /// Every recorded file is present and unchanged
+    Ok,
+    /// A recorded file no longer matches its hash
+    Corrupt,
+    /// A recorded file is gone
+    Missing,
+    /// No hashes were recorded, e.g. for games installed by older loader builds
+    Unknown,
+}
+
+/// One entry of the `verify_installed` report
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameVerification {
+    pub game_date: String,
+    pub status: VerifyStatus,
+}
+
+/// Verify every game in `installed`, at most `VERIFY_PARALLEL` at a time
+///
+/// The report is sorted by game date. A game that can't be read at all is
+/// reported as `Missing` rather than failing the whole run.
+pub async fn verify_installed(
+    installed: Vec<InstalledGame>,
+) -> Result<Vec<GameVerification>, SdrError> {
+    let permits = Arc::new(Semaphore::new(VERIFY_PARALLEL));
+    let mut tasks = JoinSet::new();
+    for game in installed {
+        let permit = permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| SdrError::Internal(format!("Verification was interrupted: {e}")))?;
+        tasks.spawn_blocking(move || {
+            let _permit = permit;
+            let status = verify_game_files(&game).unwrap_or_else(|e| {
+                tracing::warn!("couldn't verify {}: {e}", game.id);
+                VerifyStatus::Missing
+            });
+            GameVerification {
+                game_date: game.id,
+                status,
+            }
+        });
+    }
+
+    let mut report = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        report.push(result.map_err(|e| SdrError::Internal(format!("Verification failed: {e}")))?);
+    }
+    report.sort_by(|a, b| a.game_date.cmp(&b.game_date));
+    Ok(report)
+}
+
+/// Re-hash the files listed in a game's manifest
+///
+/// A missing file outranks a corrupt one, since it usually means a larger
+/// part of the install is gone.
+fn verify_game_files(game: &InstalledGame) -> Result<VerifyStatus, SdrError> {
+    let Some(files) = game
+        .manifest
+        .as_ref()
+        .map(|manifest| &manifest.files)
+        .filter(|files| !files.is_empty())
+    else {
+        return Ok(VerifyStatus::Unknown);
+    };
+
+    let mut status = VerifyStatus::Ok;
+    for (relative, expected) in files {
+        let path = install::resolve_inside(&game.install_path, relative)?;
+        if !path.is_file() {
+            return Ok(VerifyStatus::Missing);
+        }
+        if !checksum::hash_file_blocking(&path)?.eq_ignore_ascii_case(expected.trim()) {
+            status = VerifyStatus::Corrupt;
+        }
+    }
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+    use crate::test_support::game;
+
+    /// Install `game_date` with hashes recorded, as a download would
+    fn hashed_install(games_dir: &std::path::Path, game_date: &str) -> InstalledGame {
+        let dir = paths::game_dir(games_dir, game_date);
+        std::fs::create_dir_all(dir.join("client")).unwrap();
+        std::fs::write(dir.join("index.html"), b"<html>").unwrap();
+        std::fs::write(dir.join("client/game.js"), b"run()").unwrap();
+        install::record_install(&dir, &game(game_date, game_date)).unwrap();
+        install::read_installed_game(&dir, game_date.to_string()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_the_changed_game_is_reported_corrupt() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let intact = hashed_install(games_dir.path(), "2024-01-01");
+        let changed = hashed_install(games_dir.path(), "2024-01-02");
+        std::fs::write(changed.install_path.join("client/game.js"), b"tampered()").unwrap();
+
+        let report = verify_installed(vec![changed, intact]).await.unwrap();
+
+        let statuses: Vec<_> = report
+            .iter()
+            .map(|entry| (entry.game_date.as_str(), entry.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("2024-01-01", VerifyStatus::Ok),
+                ("2024-01-02", VerifyStatus::Corrupt)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_deleted_file_is_reported_missing_and_no_hashes_unknown() {
+        let games_dir = tempfile::tempdir().unwrap();
+        let removed = hashed_install(games_dir.path(), "2024-01-01");
+        std::fs::remove_file(removed.install_path.join("index.html")).unwrap();
+        let mut unhashed = hashed_install(games_dir.path(), "2024-01-02");
+        unhashed.manifest.as_mut().unwrap().files.clear();
+
+        let report = verify_installed(vec![removed, unhashed]).await.unwrap();
+
+        assert_eq!(report[0].status, VerifyStatus::Missing);
+        assert_eq!(report[1].status, VerifyStatus::Unknown);
+    }
+}